@@ -0,0 +1,293 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Authenticated encryption with associated data over the duplex sponge.
+//!
+//! The state starts with the [`DOMAIN_AEAD`] capacity, and its rate holds
+//! the key, the nonce and the lengths of the associated data and of the
+//! plaintext, so both lengths are bound to the tag without padding. After
+//! permuting, the associated data is absorbed [`RATE`] elements at a time.
+//! Every block of plaintext is then added to the rate, the resulting rate
+//! being the ciphertext, and the state is permuted again. The tag is the
+//! first element of the rate after the last permutation.
+//!
+//! The keystream only depends on the key and the nonce, so encrypting two
+//! messages with the same key and nonce reveals the difference of their
+//! plaintexts. [`HadesAead::encrypt`] takes its [`Nonce`] by value, and
+//! [`Nonce`] can't be cloned, so a nonce can't be reused by mistake.
+
+use core::fmt;
+
+use dusk_bls12_381::BlsScalar;
+use rand_core::{CryptoRng, RngCore};
+
+use crate::sponge::{capacity, DOMAIN_AEAD, RATE};
+use crate::{Error, HadesDigest, ScalarStrategy, Strategy, WIDTH};
+
+#[cfg(feature = "alloc")]
+use crate::{decode_bytes, encode_bytes};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// Nonce of a single encryption.
+///
+/// A nonce must never be used twice with the same key. It is consumed by
+/// [`HadesAead::encrypt`], and doesn't implement `Clone`, so the only way
+/// to reuse one is to build it twice from the same scalar.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Nonce(BlsScalar);
+
+impl Nonce {
+    /// Create a nonce from a scalar, which must be unique for the key it is
+    /// used with, such as a counter.
+    ///
+    /// The receiver rebuilds the nonce sent along with a ciphertext the
+    /// same way, to decrypt it.
+    pub const fn new(value: BlsScalar) -> Self {
+        Self(value)
+    }
+
+    /// Create a uniformly random nonce.
+    pub fn random<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        let mut bytes = [0u8; 64];
+        rng.fill_bytes(&mut bytes);
+
+        Self(BlsScalar::from_bytes_wide(&bytes))
+    }
+
+    /// Scalar of the nonce, to be sent along with the ciphertext.
+    pub const fn value(&self) -> BlsScalar {
+        self.0
+    }
+}
+
+/// Authenticated encryption keyed with a secret scalar.
+#[derive(Clone, Copy)]
+pub struct HadesAead {
+    key: BlsScalar,
+}
+
+impl fmt::Debug for HadesAead {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HadesAead").finish_non_exhaustive()
+    }
+}
+
+impl HadesAead {
+    /// Create an AEAD with the given secret key.
+    pub const fn new(key: BlsScalar) -> Self {
+        Self { key }
+    }
+
+    /// Encrypt `buffer` in place, returning the tag authenticating both
+    /// the ciphertext and the associated data.
+    pub fn encrypt(
+        &self,
+        nonce: Nonce,
+        associated_data: &[BlsScalar],
+        buffer: &mut [BlsScalar],
+    ) -> HadesDigest {
+        let mut state = self.start(&nonce, associated_data, buffer.len());
+
+        buffer.chunks_mut(RATE).for_each(|block| {
+            block.iter_mut().zip(&mut state[1..]).for_each(|(x, s)| {
+                *s += *x;
+                *x = *s;
+            });
+            ScalarStrategy::new().perm(&mut state);
+        });
+
+        state[1].into()
+    }
+
+    /// Decrypt `buffer` in place, checking the tag in constant time.
+    ///
+    /// If the tag doesn't match, the buffer is zeroed so the
+    /// unauthenticated plaintext is never released, and
+    /// [`Error::InvalidTag`] is returned.
+    pub fn decrypt(
+        &self,
+        nonce: &Nonce,
+        associated_data: &[BlsScalar],
+        buffer: &mut [BlsScalar],
+        tag: &HadesDigest,
+    ) -> Result<(), Error> {
+        let mut state = self.start(nonce, associated_data, buffer.len());
+
+        buffer.chunks_mut(RATE).for_each(|block| {
+            block.iter_mut().zip(&mut state[1..]).for_each(|(x, s)| {
+                let plain = *x - *s;
+                *s = *x;
+                *x = plain;
+            });
+            ScalarStrategy::new().perm(&mut state);
+        });
+
+        if HadesDigest::from(state[1]) == *tag {
+            Ok(())
+        } else {
+            buffer.iter_mut().for_each(|x| *x = BlsScalar::zero());
+            Err(Error::InvalidTag)
+        }
+    }
+
+    /// Encrypt a byte string with byte associated data, both encoded with
+    /// [`encode_bytes`].
+    #[cfg(feature = "alloc")]
+    pub fn encrypt_bytes(
+        &self,
+        nonce: Nonce,
+        associated_data: &[u8],
+        plaintext: &[u8],
+    ) -> (Vec<BlsScalar>, HadesDigest) {
+        let mut ciphertext = encode_bytes(plaintext);
+        let tag = self.encrypt(nonce, &encode_bytes(associated_data), &mut ciphertext);
+
+        (ciphertext, tag)
+    }
+
+    /// Decrypt a byte string encrypted with [`HadesAead::encrypt_bytes`].
+    ///
+    /// Returns [`Error::InvalidTag`] if the tag doesn't match, and
+    /// [`Error::InvalidEncoding`] if the authenticated plaintext doesn't
+    /// encode a byte string.
+    #[cfg(feature = "alloc")]
+    pub fn decrypt_bytes(
+        &self,
+        nonce: &Nonce,
+        associated_data: &[u8],
+        ciphertext: &[BlsScalar],
+        tag: &HadesDigest,
+    ) -> Result<Vec<u8>, Error> {
+        let mut plaintext = ciphertext.to_vec();
+        self.decrypt(nonce, &encode_bytes(associated_data), &mut plaintext, tag)?;
+
+        decode_bytes(&plaintext).ok_or(Error::InvalidEncoding)
+    }
+
+    /// Initialize the state and absorb the associated data.
+    fn start(
+        &self,
+        nonce: &Nonce,
+        associated_data: &[BlsScalar],
+        len: usize,
+    ) -> [BlsScalar; WIDTH] {
+        let mut state = [
+            capacity(DOMAIN_AEAD),
+            self.key,
+            nonce.0,
+            BlsScalar::from(associated_data.len() as u64),
+            BlsScalar::from(len as u64),
+        ];
+        ScalarStrategy::new().perm(&mut state);
+
+        associated_data.chunks(RATE).for_each(|block| {
+            block.iter().zip(&mut state[1..]).for_each(|(x, s)| *s += x);
+            ScalarStrategy::new().perm(&mut state);
+        });
+
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: BlsScalar = BlsScalar::from_raw([42, 0, 0, 0]);
+
+    fn message() -> [BlsScalar; 6] {
+        core::array::from_fn(|i| BlsScalar::from(i as u64))
+    }
+
+    #[test]
+    fn roundtrip() {
+        let aead = HadesAead::new(KEY);
+        let ad = [BlsScalar::one(); 5];
+        let nonce = Nonce::new(BlsScalar::from(7u64));
+        let value = nonce.value();
+
+        let mut buffer = message();
+        let tag = aead.encrypt(nonce, &ad, &mut buffer);
+        assert_ne!(buffer, message());
+
+        // The receiver rebuilds the nonce from the transmitted scalar
+        let nonce = Nonce::new(value);
+        let ciphertext = buffer;
+        assert_eq!(aead.decrypt(&nonce, &ad, &mut buffer, &tag), Ok(()));
+        assert_eq!(buffer, message());
+
+        // The same plaintext encrypts differently under another nonce
+        let mut other = message();
+        aead.encrypt(Nonce::new(BlsScalar::one()), &ad, &mut other);
+        assert_ne!(other, ciphertext);
+
+        // Empty messages are authenticated too
+        let nonce = Nonce::new(BlsScalar::from(8u64));
+        let tag = aead.encrypt(nonce, &[], &mut []);
+
+        let nonce = Nonce::new(BlsScalar::from(8u64));
+        assert_eq!(aead.decrypt(&nonce, &[], &mut [], &tag), Ok(()));
+        assert!(aead.decrypt(&nonce, &ad[..1], &mut [], &tag).is_err());
+    }
+
+    #[test]
+    fn tampering() {
+        let aead = HadesAead::new(KEY);
+        let ad = [BlsScalar::one(); 3];
+        let nonce = BlsScalar::from(7u64);
+
+        let mut ciphertext = message();
+        let tag = aead.encrypt(Nonce::new(nonce), &ad, &mut ciphertext);
+
+        let rejects = |key: BlsScalar, nonce: BlsScalar, ad: &[BlsScalar], tag: &HadesDigest| {
+            let mut buffer = ciphertext;
+            let result = HadesAead::new(key).decrypt(&Nonce::new(nonce), ad, &mut buffer, tag);
+
+            result == Err(Error::InvalidTag) && buffer.iter().all(|x| *x == BlsScalar::zero())
+        };
+
+        assert!(rejects(KEY + BlsScalar::one(), nonce, &ad, &tag));
+        assert!(rejects(KEY, BlsScalar::one(), &ad, &tag));
+        assert!(rejects(KEY, nonce, &ad[..2], &tag));
+        assert!(rejects(KEY, nonce, &[BlsScalar::zero(); 3], &tag));
+        assert!(rejects(KEY, nonce, &ad, &BlsScalar::one().into()));
+
+        let mut buffer = ciphertext;
+        buffer[5] += BlsScalar::one();
+        assert!(aead
+            .decrypt(&Nonce::new(nonce), &ad, &mut buffer, &tag)
+            .is_err());
+
+        let mut buffer = ciphertext;
+        assert!(aead
+            .decrypt(&Nonce::new(nonce), &ad, &mut buffer[..5], &tag)
+            .is_err());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn bytes() {
+        let aead = HadesAead::new(KEY);
+        let nonce = Nonce::new(BlsScalar::from(7u64));
+
+        let value = nonce.value();
+
+        let (ciphertext, tag) = aead.encrypt_bytes(nonce, b"header", b"note payload");
+
+        // The receiver rebuilds the nonce from the transmitted scalar
+        let nonce = Nonce::new(value);
+
+        assert_eq!(
+            aead.decrypt_bytes(&nonce, b"header", &ciphertext, &tag),
+            Ok(b"note payload".to_vec())
+        );
+        assert_eq!(
+            aead.decrypt_bytes(&nonce, b"header\0", &ciphertext, &tag),
+            Err(Error::InvalidTag)
+        );
+    }
+}
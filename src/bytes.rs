@@ -0,0 +1,283 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Injective encoding of byte strings into scalars.
+//!
+//! The bytes are split into limbs of [`LIMB_BYTES`] bytes, each read as a
+//! little-endian integer, so every limb is smaller than `2^248` and fits in
+//! the scalar field. The last limb is padded with zeroes, and the length of
+//! the byte string in bytes is appended as a last element, so strings that
+//! only differ by trailing zeroes have different encodings.
+
+use dusk_bls12_381::BlsScalar;
+
+use crate::sponge::{Sponge, DOMAIN_BYTES};
+use crate::{u64_from_buffer, HadesDigest};
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "plonk")]
+use crate::sponge::SpongeGadget;
+#[cfg(feature = "plonk")]
+use dusk_plonk::prelude::*;
+
+/// Amount of bytes encoded in a single limb.
+pub const LIMB_BYTES: usize = 31;
+
+#[cfg(feature = "plonk")]
+const LIMB_BITS: usize = LIMB_BYTES * 8;
+
+/// Encode the bytes into their limbs, followed by the length of the bytes.
+#[cfg(feature = "alloc")]
+pub fn encode_bytes(bytes: &[u8]) -> Vec<BlsScalar> {
+    let mut encoded: Vec<_> = bytes.chunks(LIMB_BYTES).map(limb).collect();
+    encoded.push(BlsScalar::from(bytes.len() as u64));
+    encoded
+}
+
+/// Decode bytes encoded with [`encode_bytes`].
+///
+/// Returns `None` if the scalars are not the canonical encoding of a byte
+/// string.
+#[cfg(feature = "alloc")]
+pub fn decode_bytes(encoded: &[BlsScalar]) -> Option<Vec<u8>> {
+    let (len, limbs) = encoded.split_last()?;
+
+    let len = len.to_bytes();
+    if len[8..].iter().any(|b| *b != 0) {
+        return None;
+    }
+    let len = usize::try_from(u64_from_buffer(&len, 0)).ok()?;
+
+    if limbs.len() != limb_count(len) {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(limbs.len() * LIMB_BYTES);
+    for limb in limbs {
+        let limb = limb.to_bytes();
+        if limb[LIMB_BYTES] != 0 {
+            return None;
+        }
+        bytes.extend_from_slice(&limb[..LIMB_BYTES]);
+    }
+
+    // The padding of the last limb must be zero
+    if bytes[len..].iter().any(|b| *b != 0) {
+        return None;
+    }
+    bytes.truncate(len);
+
+    Some(bytes)
+}
+
+/// Hash a byte string, absorbing its encoding in the [`DOMAIN_BYTES`]
+/// domain of the sponge.
+pub fn hash_bytes(bytes: &[u8]) -> HadesDigest {
+    let mut sponge = Sponge::with_domain(DOMAIN_BYTES);
+    absorb_bytes(&mut sponge, bytes);
+
+    sponge.finalize().into()
+}
+
+/// Absorb the encoding of `bytes` into the sponge.
+pub(crate) fn absorb_bytes(sponge: &mut Sponge, bytes: &[u8]) {
+    bytes
+        .chunks(LIMB_BYTES)
+        .for_each(|chunk| sponge.absorb(&[limb(chunk)]));
+    sponge.absorb(&[BlsScalar::from(bytes.len() as u64)]);
+}
+
+/// Absorb the encoding of `bytes` into the sponge, as constants of the
+/// circuit, matching [`absorb_bytes`].
+#[cfg(feature = "plonk")]
+pub(crate) fn absorb_bytes_gadget(
+    composer: &mut Composer,
+    sponge: &mut SpongeGadget,
+    bytes: &[u8],
+) {
+    bytes.chunks(LIMB_BYTES).for_each(|chunk| {
+        let limb = composer.append_constant(limb(chunk));
+        sponge.absorb(composer, &[limb]);
+    });
+
+    let len = composer.append_constant(BlsScalar::from(bytes.len() as u64));
+    sponge.absorb(composer, &[len]);
+}
+
+/// Hash a byte string of `len` bytes given its witnessed limbs, matching
+/// [`hash_bytes`].
+///
+/// Every limb is constrained to [`LIMB_BYTES`] bytes, and the padding of
+/// the last limb to be zero, so only the canonical encoding of a byte
+/// string is accepted. The length is a constant of the circuit.
+///
+/// # Panics
+///
+/// Panics if the amount of limbs doesn't match `len`.
+#[cfg(feature = "plonk")]
+pub fn hash_bytes_gadget(composer: &mut Composer, limbs: &[Witness], len: usize) -> Witness {
+    assert_eq!(limbs.len(), limb_count(len), "wrong amount of limbs");
+
+    limbs.iter().enumerate().for_each(|(i, limb)| {
+        let bits = composer.component_decomposition::<LIMB_BITS>(*limb);
+
+        let used = (len - i * LIMB_BYTES).min(LIMB_BYTES) * 8;
+        bits[used..]
+            .iter()
+            .for_each(|bit| composer.assert_equal(*bit, Composer::ZERO));
+    });
+
+    let len = composer.append_constant(BlsScalar::from(len as u64));
+
+    let mut sponge = SpongeGadget::with_domain(composer, DOMAIN_BYTES);
+    sponge.absorb(composer, limbs);
+    sponge.absorb(composer, &[len]);
+    sponge.finalize(composer)
+}
+
+/// Amount of limbs encoding `len` bytes.
+#[cfg(feature = "alloc")]
+const fn limb_count(len: usize) -> usize {
+    len.div_ceil(LIMB_BYTES)
+}
+
+/// Read up to [`LIMB_BYTES`] bytes as a little-endian integer.
+pub(crate) fn limb(chunk: &[u8]) -> BlsScalar {
+    let mut bytes = [0u8; 32];
+    bytes[..chunk.len()].copy_from_slice(chunk);
+
+    BlsScalar::from_raw([
+        u64_from_buffer(&bytes, 0),
+        u64_from_buffer(&bytes, 8),
+        u64_from_buffer(&bytes, 16),
+        u64_from_buffer(&bytes, 24),
+    ])
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+
+    const INPUTS: [&[u8]; 6] = [b"", b"\0", b"hades", b"hades\0", &[0xff; 31], &[0xff; 62]];
+
+    #[test]
+    fn encoding() {
+        INPUTS.iter().for_each(|input| {
+            let encoded = encode_bytes(input);
+
+            assert_eq!(encoded.len(), limb_count(input.len()) + 1);
+            assert_eq!(decode_bytes(&encoded).as_deref(), Some(*input));
+        });
+
+        // Trailing zeroes change the length
+        assert_ne!(encode_bytes(b"hades"), encode_bytes(b"hades\0"));
+
+        // Non-canonical encodings are rejected
+        let mut encoded = encode_bytes(b"hades");
+        encoded[1] = BlsScalar::from(4u64);
+        assert!(decode_bytes(&encoded).is_none());
+
+        let mut encoded = encode_bytes(b"hades");
+        encoded[0] += BlsScalar::from_raw([0, 0, 0, 1 << 56]);
+        assert!(decode_bytes(&encoded).is_none());
+
+        let mut encoded = encode_bytes(&[0xff; 31]);
+        encoded.insert(1, BlsScalar::zero());
+        assert!(decode_bytes(&encoded).is_none());
+
+        assert!(decode_bytes(&[]).is_none());
+    }
+
+    #[test]
+    fn hash() {
+        INPUTS.iter().for_each(|input| {
+            let mut sponge = Sponge::with_domain(DOMAIN_BYTES);
+            sponge.absorb(&encode_bytes(input));
+
+            assert_eq!(hash_bytes(input), HadesDigest::from(sponge.finalize()));
+        });
+
+        assert_ne!(hash_bytes(b"hades"), hash_bytes(b"hades\0"));
+        assert_ne!(hash_bytes(b""), crate::hash(&encode_bytes(b"")));
+    }
+
+    #[cfg(feature = "plonk")]
+    mod gadget {
+        use super::*;
+        use dusk_plonk::prelude::Error;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        const INPUT: &[u8] = b"a byte string spanning over two limbs";
+
+        struct BytesCircuit {
+            limbs: Vec<BlsScalar>,
+            hash: BlsScalar,
+        }
+
+        impl Default for BytesCircuit {
+            fn default() -> Self {
+                Self {
+                    limbs: alloc::vec![BlsScalar::zero(); limb_count(INPUT.len())],
+                    hash: BlsScalar::zero(),
+                }
+            }
+        }
+
+        impl Circuit for BytesCircuit {
+            fn circuit(&self, composer: &mut Composer) -> Result<(), Error> {
+                let limbs: Vec<_> = self
+                    .limbs
+                    .iter()
+                    .map(|limb| composer.append_witness(*limb))
+                    .collect();
+
+                let hash = hash_bytes_gadget(composer, &limbs, INPUT.len());
+
+                let public = composer.append_public(self.hash);
+                composer.assert_equal(hash, public);
+
+                Ok(())
+            }
+        }
+
+        #[test]
+        fn hash_bytes() -> Result<(), Error> {
+            let mut rng = StdRng::seed_from_u64(0xbeef);
+
+            let pp = PublicParameters::setup(1 << 12, &mut rng)?;
+            let (prover, verifier) = Compiler::compile::<BytesCircuit>(&pp, b"hash_bytes")?;
+
+            let mut limbs = encode_bytes(INPUT);
+            limbs.pop();
+
+            let circuit = BytesCircuit {
+                limbs,
+                hash: super::hash_bytes(INPUT).into(),
+            };
+            let (proof, public_inputs) = prover.prove(&mut rng, &circuit)?;
+            verifier.verify(&proof, &public_inputs)?;
+
+            // A non-zero padding byte is rejected, even with a matching hash
+            let mut limbs = circuit.limbs.clone();
+            limbs[1] += BlsScalar::from_raw([0, 0, 0, 1 << 48]);
+
+            let mut sponge = Sponge::with_domain(DOMAIN_BYTES);
+            sponge.absorb(&limbs);
+            sponge.absorb(&[BlsScalar::from(INPUT.len() as u64)]);
+
+            let circuit = BytesCircuit {
+                limbs,
+                hash: sponge.finalize(),
+            };
+            assert!(prover.prove(&mut rng, &circuit).is_err());
+
+            Ok(())
+        }
+    }
+}
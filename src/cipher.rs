@@ -0,0 +1,379 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Keyed HadesMiMC block cipher.
+//!
+//! The rounds are the ones of the `Hades252` permutation, where the round
+//! key is added to the state together with the round constants, and a last
+//! round key is added after the last round. The round keys follow a linear
+//! key schedule: the first one is the master key, and every following one
+//! is the previous one multiplied by the MDS matrix.
+//!
+//! With a zero key every round key is zero, and encrypting a block is
+//! applying the permutation to it.
+
+use core::fmt;
+
+use dusk_bls12_381::BlsScalar;
+
+use crate::mds_matrix::MDS_MATRIX;
+use crate::round_constants::ROUND_CONSTANTS;
+use crate::{PARTIAL_ROUNDS, TOTAL_FULL_ROUNDS, WIDTH};
+
+#[cfg(feature = "plonk")]
+use dusk_plonk::prelude::*;
+
+/// Amount of rounds of the cipher.
+const ROUNDS: usize = TOTAL_FULL_ROUNDS + PARTIAL_ROUNDS;
+
+/// Inverse of `5` modulo `p - 1`, inverting the S-box.
+const INVERSE_SBOX: [u64; 4] = [
+    0x33333332cccccccd,
+    0x217f0e679998f199,
+    0xe14a56699d73f002,
+    0x2e5f0fbadd72321c,
+];
+
+/// Keyed HadesMiMC block cipher over blocks of `WIDTH` scalars.
+#[derive(Clone)]
+pub struct HadesCipher {
+    round_keys: [[BlsScalar; WIDTH]; ROUNDS + 1],
+}
+
+impl fmt::Debug for HadesCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HadesCipher").finish_non_exhaustive()
+    }
+}
+
+impl HadesCipher {
+    /// Create a cipher with the given secret key, expanding its round keys.
+    pub fn new(key: &[BlsScalar; WIDTH]) -> Self {
+        let mut round_keys = [[BlsScalar::zero(); WIDTH]; ROUNDS + 1];
+
+        round_keys[0] = *key;
+        for i in 1..=ROUNDS {
+            round_keys[i] = mul_matrix(&MDS_MATRIX, &round_keys[i - 1]);
+        }
+
+        Self { round_keys }
+    }
+
+    /// Encrypt a block in place.
+    pub fn encrypt(&self, block: &mut [BlsScalar; WIDTH]) {
+        for round in 0..ROUNDS {
+            add(block, &round_constants(round));
+            add(block, &self.round_keys[round]);
+
+            if is_full(round) {
+                block.iter_mut().for_each(|x| *x = sbox(x));
+            } else {
+                block[WIDTH - 1] = sbox(&block[WIDTH - 1]);
+            }
+
+            *block = mul_matrix(&MDS_MATRIX, block);
+        }
+
+        add(block, &self.round_keys[ROUNDS]);
+    }
+
+    /// Decrypt a block in place.
+    pub fn decrypt(&self, block: &mut [BlsScalar; WIDTH]) {
+        let inverse = inverse_mds();
+
+        sub(block, &self.round_keys[ROUNDS]);
+
+        for round in (0..ROUNDS).rev() {
+            *block = mul_matrix(&inverse, block);
+
+            if is_full(round) {
+                block.iter_mut().for_each(|x| *x = x.pow(&INVERSE_SBOX));
+            } else {
+                block[WIDTH - 1] = block[WIDTH - 1].pow(&INVERSE_SBOX);
+            }
+
+            sub(block, &self.round_keys[round]);
+            sub(block, &round_constants(round));
+        }
+    }
+}
+
+/// Encrypt a block inside a plonk circuit with a witnessed key, matching
+/// [`HadesCipher::encrypt`].
+///
+/// The round keys are expanded in the circuit, so the key can stay
+/// private.
+#[cfg(feature = "plonk")]
+pub fn cipher_gadget(
+    composer: &mut Composer,
+    key: &[Witness; WIDTH],
+    block: &[Witness; WIDTH],
+) -> [Witness; WIDTH] {
+    let mut key = *key;
+    let mut block = *block;
+
+    for round in 0..ROUNDS {
+        let constants = round_constants(round);
+        block
+            .iter_mut()
+            .zip(key.iter())
+            .zip(constants.iter())
+            .for_each(|((x, k), c)| {
+                let constraint = Constraint::new().left(1).a(*x).right(1).b(*k).constant(*c);
+                *x = composer.gate_add(constraint);
+            });
+
+        if is_full(round) {
+            block
+                .iter_mut()
+                .for_each(|x| *x = sbox_gadget(composer, *x));
+        } else {
+            block[WIDTH - 1] = sbox_gadget(composer, block[WIDTH - 1]);
+        }
+
+        block = mds_gadget(composer, &block);
+        key = mds_gadget(composer, &key);
+    }
+
+    block.iter_mut().zip(key.iter()).for_each(|(x, k)| {
+        let constraint = Constraint::new().left(1).a(*x).right(1).b(*k);
+        *x = composer.gate_add(constraint);
+    });
+
+    block
+}
+
+/// Whether the S-box is applied to every element in the given round.
+const fn is_full(round: usize) -> bool {
+    round < TOTAL_FULL_ROUNDS / 2 || round >= TOTAL_FULL_ROUNDS / 2 + PARTIAL_ROUNDS
+}
+
+/// Round constants of the given round, as consumed by the permutation.
+fn round_constants(round: usize) -> [BlsScalar; WIDTH] {
+    let mut constants = [BlsScalar::zero(); WIDTH];
+    constants.copy_from_slice(&ROUND_CONSTANTS[round * WIDTH..(round + 1) * WIDTH]);
+    constants
+}
+
+fn sbox(x: &BlsScalar) -> BlsScalar {
+    x.square().square() * x
+}
+
+fn add(block: &mut [BlsScalar; WIDTH], other: &[BlsScalar; WIDTH]) {
+    block.iter_mut().zip(other).for_each(|(x, y)| *x += y);
+}
+
+fn sub(block: &mut [BlsScalar; WIDTH], other: &[BlsScalar; WIDTH]) {
+    block.iter_mut().zip(other).for_each(|(x, y)| *x -= y);
+}
+
+fn mul_matrix(
+    matrix: &[[BlsScalar; WIDTH]; WIDTH],
+    values: &[BlsScalar; WIDTH],
+) -> [BlsScalar; WIDTH] {
+    let mut result = [BlsScalar::zero(); WIDTH];
+
+    result.iter_mut().zip(matrix).for_each(|(r, row)| {
+        *r = row
+            .iter()
+            .zip(values)
+            .fold(BlsScalar::zero(), |sum, (m, v)| sum + m * v);
+    });
+
+    result
+}
+
+/// Invert the MDS matrix with Gauss-Jordan elimination.
+fn inverse_mds() -> [[BlsScalar; WIDTH]; WIDTH] {
+    let mut matrix = MDS_MATRIX;
+    let mut inverse = [[BlsScalar::zero(); WIDTH]; WIDTH];
+    (0..WIDTH).for_each(|i| inverse[i][i] = BlsScalar::one());
+
+    for col in 0..WIDTH {
+        let pivot = (col..WIDTH)
+            .find(|row| matrix[*row][col] != BlsScalar::zero())
+            .expect("the MDS matrix is invertible");
+        matrix.swap(col, pivot);
+        inverse.swap(col, pivot);
+
+        let factor = matrix[col][col].invert().unwrap();
+        (0..WIDTH).for_each(|j| {
+            matrix[col][j] *= factor;
+            inverse[col][j] *= factor;
+        });
+
+        for row in (0..WIDTH).filter(|row| *row != col) {
+            let factor = matrix[row][col];
+            (0..WIDTH).for_each(|j| {
+                let (m, i) = (matrix[col][j], inverse[col][j]);
+                matrix[row][j] -= factor * m;
+                inverse[row][j] -= factor * i;
+            });
+        }
+    }
+
+    inverse
+}
+
+#[cfg(feature = "plonk")]
+fn sbox_gadget(composer: &mut Composer, x: Witness) -> Witness {
+    let constraint = Constraint::new().mult(1).a(x).b(x);
+    let x2 = composer.gate_mul(constraint);
+
+    let constraint = Constraint::new().mult(1).a(x2).b(x2);
+    let x4 = composer.gate_mul(constraint);
+
+    let constraint = Constraint::new().mult(1).a(x4).b(x);
+    composer.gate_mul(constraint)
+}
+
+#[cfg(feature = "plonk")]
+fn mds_gadget(composer: &mut Composer, values: &[Witness; WIDTH]) -> [Witness; WIDTH] {
+    let mut result = [Composer::ZERO; WIDTH];
+
+    result
+        .iter_mut()
+        .zip(MDS_MATRIX.iter())
+        .for_each(|(r, row)| {
+            let constraint = Constraint::new()
+                .left(row[0])
+                .a(values[0])
+                .right(row[1])
+                .b(values[1])
+                .fourth(row[2])
+                .d(values[2]);
+            *r = composer.gate_add(constraint);
+
+            let constraint = Constraint::new()
+                .left(row[3])
+                .a(values[3])
+                .right(row[4])
+                .b(values[4])
+                .fourth(1)
+                .d(*r);
+            *r = composer.gate_add(constraint);
+        });
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ScalarStrategy, Strategy};
+
+    const BLOCK: [BlsScalar; WIDTH] = [
+        BlsScalar::zero(),
+        BlsScalar::one(),
+        BlsScalar::from_raw([2, 0, 0, 0]),
+        BlsScalar::from_raw([3, 0, 0, 0]),
+        BlsScalar::from_raw([4, 0, 0, 0]),
+    ];
+
+    const KEY: [BlsScalar; WIDTH] = [BlsScalar::from_raw([42, 0, 0, 0]); WIDTH];
+
+    #[test]
+    fn roundtrip() {
+        let cipher = HadesCipher::new(&KEY);
+
+        let mut block = BLOCK;
+        cipher.encrypt(&mut block);
+        assert_ne!(block, BLOCK);
+
+        cipher.decrypt(&mut block);
+        assert_eq!(block, BLOCK);
+
+        let mut other = BLOCK;
+        let mut key = KEY;
+        key[4] += BlsScalar::one();
+        HadesCipher::new(&key).encrypt(&mut other);
+        cipher.encrypt(&mut block);
+        assert_ne!(block, other);
+    }
+
+    #[test]
+    fn unkeyed() {
+        let mut block = BLOCK;
+        HadesCipher::new(&[BlsScalar::zero(); WIDTH]).encrypt(&mut block);
+
+        let mut expected = BLOCK;
+        ScalarStrategy::new().perm(&mut expected);
+
+        assert_eq!(block, expected);
+    }
+
+    #[test]
+    fn inverse() {
+        let inverse = inverse_mds();
+        assert_eq!(
+            mul_matrix(&inverse, &mul_matrix(&MDS_MATRIX, &BLOCK)),
+            BLOCK
+        );
+
+        let x = BlsScalar::from(42u64);
+        assert_eq!(sbox(&x).pow(&INVERSE_SBOX), x);
+    }
+
+    #[cfg(feature = "plonk")]
+    mod gadget {
+        use super::*;
+        use dusk_plonk::prelude::Error;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        #[derive(Default)]
+        struct CipherCircuit {
+            key: [BlsScalar; WIDTH],
+            block: [BlsScalar; WIDTH],
+            ciphertext: [BlsScalar; WIDTH],
+        }
+
+        impl Circuit for CipherCircuit {
+            fn circuit(&self, composer: &mut Composer) -> Result<(), Error> {
+                let key = self.key.map(|x| composer.append_witness(x));
+                let block = self.block.map(|x| composer.append_witness(x));
+
+                let ciphertext = cipher_gadget(composer, &key, &block);
+
+                ciphertext
+                    .iter()
+                    .zip(self.ciphertext.iter())
+                    .for_each(|(c, expected)| {
+                        let public = composer.append_public(*expected);
+                        composer.assert_equal(*c, public);
+                    });
+
+                Ok(())
+            }
+        }
+
+        #[test]
+        fn encryption() -> Result<(), Error> {
+            let mut rng = StdRng::seed_from_u64(0xbeef);
+
+            let pp = PublicParameters::setup(1 << 12, &mut rng)?;
+            let (prover, verifier) = Compiler::compile::<CipherCircuit>(&pp, b"cipher")?;
+
+            let mut ciphertext = BLOCK;
+            HadesCipher::new(&KEY).encrypt(&mut ciphertext);
+
+            let circuit = CipherCircuit {
+                key: KEY,
+                block: BLOCK,
+                ciphertext,
+            };
+            let (proof, public_inputs) = prover.prove(&mut rng, &circuit)?;
+            verifier.verify(&proof, &public_inputs)?;
+
+            let mut key = KEY;
+            key[0] += BlsScalar::one();
+            let circuit = CipherCircuit { key, ..circuit };
+            assert!(prover.prove(&mut rng, &circuit).is_err());
+
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,311 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Ready-made plonk circuits, and the keys to prove and verify them.
+//!
+//! Compiling a circuit is much more expensive than proving it, so the keys
+//! can be written to disk with the `std` feature and read back instead of
+//! compiling the circuit on every run.
+
+use alloc::vec::Vec;
+use dusk_bls12_381::BlsScalar;
+use dusk_plonk::prelude::*;
+use rand_core::{CryptoRng, RngCore};
+
+use crate::{hash, hash_gadget, opening_gadget, HadesDigest, Opening};
+
+#[cfg(feature = "std")]
+use std::fs::{self, File};
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
+
+/// Circuit proving the knowledge of `N` scalars hashing to a public digest.
+///
+/// The preimage is private, and the digest of [`hash`] is the only public
+/// input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PreimageCircuit<const N: usize> {
+    preimage: [BlsScalar; N],
+}
+
+impl<const N: usize> Default for PreimageCircuit<N> {
+    fn default() -> Self {
+        Self::new([BlsScalar::zero(); N])
+    }
+}
+
+impl<const N: usize> PreimageCircuit<N> {
+    /// Create the circuit for the given preimage.
+    pub const fn new(preimage: [BlsScalar; N]) -> Self {
+        Self { preimage }
+    }
+
+    /// Digest of the preimage, the public input of the circuit.
+    pub fn digest(&self) -> HadesDigest {
+        hash(&self.preimage)
+    }
+}
+
+impl<const N: usize> Circuit for PreimageCircuit<N> {
+    fn circuit(&self, composer: &mut Composer) -> Result<(), Error> {
+        let preimage = self.preimage.map(|x| composer.append_witness(x));
+        let digest = hash_gadget(composer, &preimage);
+
+        let public = composer.append_public(BlsScalar::from(self.digest()));
+        composer.assert_equal(digest, public);
+
+        Ok(())
+    }
+}
+
+/// Circuit proving that a private leaf belongs to the [`MerkleTree`] of
+/// height `H` with a public root.
+///
+/// The leaf and its opening are private, and the root is the only public
+/// input.
+///
+/// [`MerkleTree`]: crate::MerkleTree
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MembershipCircuit<const H: usize> {
+    leaf: BlsScalar,
+    opening: Opening<H>,
+}
+
+impl<const H: usize> MembershipCircuit<H> {
+    /// Create the circuit for the given leaf and its opening.
+    pub const fn new(leaf: BlsScalar, opening: Opening<H>) -> Self {
+        Self { leaf, opening }
+    }
+
+    /// Root of the tree, the public input of the circuit.
+    pub fn root(&self) -> HadesDigest {
+        self.opening.root(&self.leaf)
+    }
+}
+
+impl<const H: usize> Circuit for MembershipCircuit<H> {
+    fn circuit(&self, composer: &mut Composer) -> Result<(), Error> {
+        let leaf = composer.append_witness(self.leaf);
+        let root = opening_gadget(composer, &self.opening, leaf);
+
+        let public = composer.append_public(BlsScalar::from(self.root()));
+        composer.assert_equal(root, public);
+
+        Ok(())
+    }
+}
+
+/// Prover and verifier keys of a compiled circuit.
+pub struct CircuitKeys {
+    prover: Prover,
+    verifier: Verifier,
+}
+
+impl CircuitKeys {
+    /// Compile the circuit `C` with the given parameters and label.
+    pub fn compile<C: Circuit>(pp: &PublicParameters, label: &[u8]) -> Result<Self, Error> {
+        let (prover, verifier) = Compiler::compile::<C>(pp, label)?;
+        Ok(Self { prover, verifier })
+    }
+
+    /// Key proving the circuit.
+    pub const fn prover(&self) -> &Prover {
+        &self.prover
+    }
+
+    /// Key verifying the proofs of the circuit.
+    pub const fn verifier(&self) -> &Verifier {
+        &self.verifier
+    }
+
+    /// Prove the circuit, returning the proof and its public inputs.
+    pub fn prove<C, R>(&self, rng: &mut R, circuit: &C) -> Result<(Proof, Vec<BlsScalar>), Error>
+    where
+        C: Circuit,
+        R: RngCore + CryptoRng,
+    {
+        self.prover.prove(rng, circuit)
+    }
+
+    /// Verify a proof of the circuit against its public inputs.
+    pub fn verify(&self, proof: &Proof, public_inputs: &[BlsScalar]) -> Result<(), Error> {
+        self.verifier.verify(proof, public_inputs)
+    }
+}
+
+#[cfg(feature = "std")]
+impl CircuitKeys {
+    /// Write the prover and verifier keys to their files.
+    ///
+    /// Every file is written next to its path and atomically moved over
+    /// it, so a crash never leaves a truncated key behind.
+    pub fn write<P, Q>(&self, prover: P, verifier: Q) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        write_atomic(prover.as_ref(), &self.prover.to_bytes())?;
+        write_atomic(verifier.as_ref(), &self.verifier.to_bytes())
+    }
+
+    /// Read the keys written by [`CircuitKeys::write`].
+    pub fn read<P, Q>(prover: P, verifier: Q) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let bytes = fs::read(prover)?;
+        let prover = Prover::try_from_bytes(bytes).map_err(invalid_data)?;
+
+        Ok(Self {
+            prover,
+            verifier: read_verifier(verifier)?,
+        })
+    }
+
+    /// Read the keys of the circuit `C` if both files exist, or compile the
+    /// circuit and write its keys otherwise.
+    pub fn read_or_compile<C, P, Q>(
+        pp: &PublicParameters,
+        label: &[u8],
+        prover: P,
+        verifier: Q,
+    ) -> io::Result<Self>
+    where
+        C: Circuit,
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        if prover.as_ref().exists() && verifier.as_ref().exists() {
+            return Self::read(prover, verifier);
+        }
+
+        let keys = Self::compile::<C>(pp, label).map_err(invalid_data)?;
+        keys.write(prover, verifier)?;
+
+        Ok(keys)
+    }
+}
+
+/// Read a verifier key written by [`CircuitKeys::write`], for the parties
+/// that only verify proofs.
+#[cfg(feature = "std")]
+pub fn read_verifier<P: AsRef<Path>>(path: P) -> io::Result<Verifier> {
+    let bytes = fs::read(path)?;
+    Verifier::try_from_bytes(bytes).map_err(invalid_data)
+}
+
+#[cfg(feature = "std")]
+fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_os_string();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let mut file = File::create(&tmp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+
+    fs::rename(&tmp, path)
+}
+
+#[cfg(feature = "std")]
+fn invalid_data(error: Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, std::format!("{:?}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MerkleTree;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    const H: usize = 4;
+
+    fn setup() -> Result<PublicParameters, Error> {
+        PublicParameters::setup(1 << 13, &mut StdRng::seed_from_u64(0xbeef))
+    }
+
+    #[test]
+    fn preimage() -> Result<(), Error> {
+        let mut rng = StdRng::seed_from_u64(0xbeef);
+
+        let keys = CircuitKeys::compile::<PreimageCircuit<3>>(&setup()?, b"preimage")?;
+
+        let circuit = PreimageCircuit::new([BlsScalar::one(); 3]);
+        let (proof, public_inputs) = keys.prove(&mut rng, &circuit)?;
+
+        assert_eq!(
+            public_inputs,
+            alloc::vec![BlsScalar::from(circuit.digest())]
+        );
+        keys.verify(&proof, &public_inputs)?;
+
+        let other = PreimageCircuit::new([BlsScalar::zero(); 3]).digest();
+        assert!(keys.verify(&proof, &[BlsScalar::from(other)]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn membership() -> Result<(), Error> {
+        let mut rng = StdRng::seed_from_u64(0xbeef);
+
+        let keys = CircuitKeys::compile::<MembershipCircuit<H>>(&setup()?, b"membership")?;
+
+        let leaves: Vec<_> = (0..5u64).map(BlsScalar::from).collect();
+        let tree = MerkleTree::<H>::from_leaves(&leaves).unwrap();
+
+        let circuit = MembershipCircuit::new(leaves[3], tree.opening(3).unwrap().unwrap());
+        assert_eq!(circuit.root(), tree.root().unwrap());
+
+        let (proof, _) = keys.prove(&mut rng, &circuit)?;
+        keys.verify(&proof, &[BlsScalar::from(tree.root().unwrap())])?;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn keys() -> io::Result<()> {
+        let path = |name: &str| {
+            let path = std::env::temp_dir().join(std::format!(
+                "dusk-hades-{}-{}.key",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_file(&path);
+            path
+        };
+        let (prover, verifier) = (path("prover"), path("verifier"));
+
+        let pp = setup().map_err(invalid_data)?;
+        let compiled = CircuitKeys::read_or_compile::<PreimageCircuit<3>, _, _>(
+            &pp,
+            b"preimage",
+            &prover,
+            &verifier,
+        )?;
+
+        let keys = CircuitKeys::read(&prover, &verifier)?;
+        assert_eq!(keys.prover().to_bytes(), compiled.prover().to_bytes());
+        assert_eq!(keys.verifier().to_bytes(), compiled.verifier().to_bytes());
+
+        // The keys read from disk prove and verify as the compiled ones
+        let mut rng = StdRng::seed_from_u64(0xbeef);
+        let circuit = PreimageCircuit::new([BlsScalar::one(); 3]);
+
+        let (proof, public_inputs) = keys.prove(&mut rng, &circuit).map_err(invalid_data)?;
+        read_verifier(&verifier)?
+            .verify(&proof, &public_inputs)
+            .map_err(invalid_data)?;
+
+        fs::remove_file(&prover)?;
+        fs::remove_file(&verifier)
+    }
+}
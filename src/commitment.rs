@@ -0,0 +1,176 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Hiding commitments to any amount of scalars.
+//!
+//! The blinder is absorbed before the values in the [`DOMAIN_COMMITMENT`]
+//! domain of the sponge. The commitment is binding as long as the sponge is
+//! collision resistant, and hiding as long as the blinder is secret and
+//! uniformly random.
+
+use dusk_bls12_381::BlsScalar;
+use rand_core::{CryptoRng, RngCore};
+
+use crate::sponge::{Sponge, DOMAIN_COMMITMENT};
+use crate::HadesDigest;
+
+#[cfg(feature = "plonk")]
+use crate::SpongeGadget;
+#[cfg(feature = "plonk")]
+use dusk_plonk::prelude::*;
+
+/// Hiding commitment to a list of scalars.
+///
+/// It is opened by revealing the values and the blinder used to compute
+/// it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Commitment(HadesDigest);
+
+impl Commitment {
+    /// Commit to `values` with a random blinder, returning the commitment
+    /// along with the blinder opening it.
+    pub fn commit<R: RngCore + CryptoRng>(rng: &mut R, values: &[BlsScalar]) -> (Self, BlsScalar) {
+        let mut bytes = [0u8; 64];
+        rng.fill_bytes(&mut bytes);
+        let blinder = BlsScalar::from_bytes_wide(&bytes);
+
+        (Self::with_blinder(values, &blinder), blinder)
+    }
+
+    /// Commit to `values` with the given blinder.
+    pub fn with_blinder(values: &[BlsScalar], blinder: &BlsScalar) -> Self {
+        let mut sponge = Sponge::with_domain(DOMAIN_COMMITMENT);
+        sponge.absorb(&[*blinder]);
+        sponge.absorb(values);
+
+        Self(sponge.finalize().into())
+    }
+
+    /// Check, in constant time, that `values` and `blinder` open the
+    /// commitment.
+    pub fn verify(&self, values: &[BlsScalar], blinder: &BlsScalar) -> bool {
+        Self::with_blinder(values, blinder) == *self
+    }
+
+    /// Digest held by the commitment.
+    pub const fn as_digest(&self) -> &HadesDigest {
+        &self.0
+    }
+}
+
+impl From<HadesDigest> for Commitment {
+    fn from(digest: HadesDigest) -> Self {
+        Self(digest)
+    }
+}
+
+impl From<Commitment> for HadesDigest {
+    fn from(commitment: Commitment) -> Self {
+        commitment.0
+    }
+}
+
+impl From<Commitment> for BlsScalar {
+    fn from(commitment: Commitment) -> Self {
+        commitment.0.into()
+    }
+}
+
+/// Compute, inside a plonk circuit, the commitment to the witnessed
+/// `values` with the witnessed `blinder`, matching
+/// [`Commitment::with_blinder`].
+///
+/// The returned witness is unconstrained: the caller decides whether to
+/// append it as a public input, compare it to another witness, or feed it to
+/// another gadget.
+#[cfg(feature = "plonk")]
+pub fn commitment_gadget(composer: &mut Composer, values: &[Witness], blinder: Witness) -> Witness {
+    let mut sponge = SpongeGadget::with_domain(composer, DOMAIN_COMMITMENT);
+    sponge.absorb(composer, &[blinder]);
+    sponge.absorb(composer, values);
+    sponge.finalize(composer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    const VALUES: [BlsScalar; 3] = [BlsScalar::one(), BlsScalar::one(), BlsScalar::zero()];
+
+    #[test]
+    fn commitment() {
+        let mut rng = StdRng::seed_from_u64(0xbeef);
+
+        let (commitment, blinder) = Commitment::commit(&mut rng, &VALUES);
+        assert!(commitment.verify(&VALUES, &blinder));
+        assert!(!commitment.verify(&VALUES[..2], &blinder));
+        assert!(!commitment.verify(&VALUES, &(blinder + BlsScalar::one())));
+
+        // The same values are hidden behind different blinders
+        let (other, _) = Commitment::commit(&mut rng, &VALUES);
+        assert_ne!(commitment, other);
+
+        // A commitment is not the hash of the blinder followed by the values
+        let mut input = [blinder; 4];
+        input[1..].copy_from_slice(&VALUES);
+        assert_ne!(*commitment.as_digest(), crate::hash(&input));
+    }
+
+    #[cfg(feature = "plonk")]
+    mod gadget {
+        use super::*;
+        use dusk_plonk::prelude::Error;
+
+        #[derive(Default)]
+        struct CommitmentCircuit {
+            values: [BlsScalar; 3],
+            blinder: BlsScalar,
+            commitment: Commitment,
+        }
+
+        impl Circuit for CommitmentCircuit {
+            fn circuit(&self, composer: &mut Composer) -> Result<(), Error> {
+                let values = self.values.map(|x| composer.append_witness(x));
+                let blinder = composer.append_witness(self.blinder);
+
+                let computed = commitment_gadget(composer, &values, blinder);
+
+                let commitment = composer.append_public(BlsScalar::from(self.commitment));
+                composer.assert_equal(computed, commitment);
+
+                Ok(())
+            }
+        }
+
+        #[test]
+        fn opening() -> Result<(), Error> {
+            let mut rng = StdRng::seed_from_u64(0xbeef);
+
+            let pp = PublicParameters::setup(1 << 11, &mut rng)?;
+            let (prover, verifier) = Compiler::compile::<CommitmentCircuit>(&pp, b"commitment")?;
+
+            let (commitment, blinder) = Commitment::commit(&mut rng, &VALUES);
+
+            let circuit = CommitmentCircuit {
+                values: VALUES,
+                blinder,
+                commitment,
+            };
+            let (proof, public_inputs) = prover.prove(&mut rng, &circuit)?;
+            verifier.verify(&proof, &public_inputs)?;
+
+            let circuit = CommitmentCircuit {
+                blinder: blinder + BlsScalar::one(),
+                ..circuit
+            };
+            assert!(prover.prove(&mut rng, &circuit).is_err());
+
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,42 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Small circuit components shared by the gadgets of this crate.
+
+use dusk_bls12_381::BlsScalar;
+use dusk_plonk::prelude::*;
+
+/// Append a boolean witness that is `1` if `value` is zero and `0`
+/// otherwise.
+///
+/// The prover supplies the inverse of `value` (or zero), and the circuit
+/// checks `value · is_zero = 0` with `is_zero = 1 - value · inverse`.
+pub(crate) fn is_zero(composer: &mut Composer, value: Witness) -> Witness {
+    let inverse = composer[value].invert().unwrap_or(BlsScalar::zero());
+    let inverse = composer.append_witness(inverse);
+
+    let constraint = Constraint::new().mult(1).a(value).b(inverse);
+    let product = composer.gate_mul(constraint);
+
+    let constraint = Constraint::new()
+        .left(-BlsScalar::one())
+        .a(product)
+        .constant(BlsScalar::one());
+    let is_zero = composer.gate_add(constraint);
+
+    let constraint = Constraint::new().mult(1).a(value).b(is_zero);
+    composer.append_gate(constraint);
+
+    is_zero
+}
+
+/// Constrain `value` to be smaller than `2^N`.
+///
+/// `N` must be strictly smaller than the bit size of the scalar field
+/// modulus, so the decomposition is unique.
+pub(crate) fn range<const N: usize>(composer: &mut Composer, value: Witness) {
+    let _ = composer.component_decomposition::<N>(value);
+}
@@ -0,0 +1,46 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use core::fmt;
+
+/// Errors returned by the fallible operations of this crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The tree has no room left for another leaf.
+    TreeFull,
+    /// The value is already stored in the tree.
+    DuplicateValue,
+    /// The value doesn't fit in the amount of bits supported by the tree.
+    ValueOutOfRange,
+    /// The bytes are not a canonical encoding.
+    InvalidEncoding,
+    /// The authentication tag doesn't match.
+    InvalidTag,
+    /// Every one-time key of the signing key was used.
+    KeysExhausted,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::TreeFull => write!(f, "the tree is full"),
+            Error::DuplicateValue => write!(f, "the value is already in the tree"),
+            Error::ValueOutOfRange => write!(f, "the value is out of range"),
+            Error::InvalidEncoding => write!(f, "the encoding is invalid"),
+            Error::InvalidTag => write!(f, "the authentication tag is invalid"),
+            Error::KeysExhausted => write!(f, "every one-time key was used"),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
+#[cfg(feature = "std")]
+impl From<Error> for std::io::Error {
+    fn from(err: Error) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, err)
+    }
+}
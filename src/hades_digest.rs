@@ -0,0 +1,298 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Output of the hash functions and roots of the trees.
+
+use core::fmt;
+use core::str::FromStr;
+
+use dusk_bls12_381::BlsScalar;
+use subtle::{Choice, ConstantTimeEq};
+
+use crate::Error;
+
+#[cfg(feature = "rkyv-impl")]
+use bytecheck::CheckBytes;
+#[cfg(feature = "rkyv-impl")]
+use rkyv::{Archive, Archived, Deserialize, Infallible, Serialize};
+
+/// Output of a hash, distinct from the other scalars.
+///
+/// It is encoded as the 32 canonical little-endian bytes of the scalar,
+/// and displayed as their lowercase hex. Equality is checked in constant
+/// time.
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(feature = "rkyv-impl", derive(Archive, Deserialize, Serialize))]
+pub struct HadesDigest(BlsScalar);
+
+impl HadesDigest {
+    /// Size of the encoded digest in bytes.
+    pub const SIZE: usize = 32;
+
+    /// Scalar held by the digest.
+    pub const fn as_scalar(&self) -> &BlsScalar {
+        &self.0
+    }
+
+    /// Canonical encoding of the digest.
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        self.0.to_bytes()
+    }
+
+    /// Decode a digest, rejecting non-canonical encodings.
+    pub fn from_bytes(bytes: &[u8; Self::SIZE]) -> Result<Self, Error> {
+        Option::<BlsScalar>::from(BlsScalar::from_bytes(bytes))
+            .map(Self)
+            .ok_or(Error::InvalidEncoding)
+    }
+}
+
+impl From<BlsScalar> for HadesDigest {
+    fn from(scalar: BlsScalar) -> Self {
+        Self(scalar)
+    }
+}
+
+impl From<HadesDigest> for BlsScalar {
+    fn from(digest: HadesDigest) -> Self {
+        digest.0
+    }
+}
+
+impl ConstantTimeEq for HadesDigest {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.ct_eq(&other.0)
+    }
+}
+
+impl PartialEq for HadesDigest {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl Eq for HadesDigest {}
+
+impl fmt::Display for HadesDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.to_bytes()
+            .iter()
+            .try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
+}
+
+impl fmt::Debug for HadesDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HadesDigest({})", self)
+    }
+}
+
+impl FromStr for HadesDigest {
+    type Err = Error;
+
+    /// Decode a digest from the hex of its canonical encoding.
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let s = s.as_bytes();
+        if s.len() != 2 * Self::SIZE {
+            return Err(Error::InvalidEncoding);
+        }
+
+        let mut bytes = [0u8; Self::SIZE];
+        for (byte, pair) in bytes.iter_mut().zip(s.chunks(2)) {
+            *byte = (nibble(pair[0])? << 4) | nibble(pair[1])?;
+        }
+
+        Self::from_bytes(&bytes)
+    }
+}
+
+fn nibble(c: u8) -> Result<u8, Error> {
+    match c {
+        b'0'..=b'9' => Ok(c - b'0'),
+        b'a'..=b'f' => Ok(c - b'a' + 10),
+        b'A'..=b'F' => Ok(c - b'A' + 10),
+        _ => Err(Error::InvalidEncoding),
+    }
+}
+
+/// Archived digests are only valid if they hold a canonical scalar, so a
+/// validated archive can't hold a digest that [`HadesDigest::from_bytes`]
+/// would reject.
+#[cfg(feature = "rkyv-impl")]
+impl<C: ?Sized> CheckBytes<C> for ArchivedHadesDigest {
+    type Error = Error;
+
+    unsafe fn check_bytes<'a>(value: *const Self, context: &mut C) -> Result<&'a Self, Error> {
+        let scalar = core::ptr::addr_of!((*value).0);
+        let scalar = Archived::<BlsScalar>::check_bytes(scalar, context)
+            .map_err(|_| Error::InvalidEncoding)?;
+
+        // The archived limbs are in Montgomery form, and only canonical
+        // limbs survive a round-trip through the canonical encoding
+        let scalar: BlsScalar = scalar
+            .deserialize(&mut Infallible)
+            .map_err(|_| Error::InvalidEncoding)?;
+        if HadesDigest::from_bytes(&scalar.to_bytes())?.0 != scalar {
+            return Err(Error::InvalidEncoding);
+        }
+
+        Ok(&*value)
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use super::*;
+
+    use serde::de::{self, Visitor};
+    use serde::{Deserializer, Serializer};
+
+    impl serde::Serialize for HadesDigest {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            if serializer.is_human_readable() {
+                serializer.collect_str(self)
+            } else {
+                serializer.serialize_bytes(&self.to_bytes())
+            }
+        }
+    }
+
+    struct DigestVisitor;
+
+    impl<'de> Visitor<'de> for DigestVisitor {
+        type Value = HadesDigest;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a canonical encoding of a hades digest")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            v.parse().map_err(E::custom)
+        }
+
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+            let bytes = v
+                .try_into()
+                .map_err(|_| E::invalid_length(v.len(), &self))?;
+            HadesDigest::from_bytes(bytes).map_err(E::custom)
+        }
+    }
+
+    impl<'de> serde::Deserialize<'de> for HadesDigest {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            if deserializer.is_human_readable() {
+                deserializer.deserialize_str(DigestVisitor)
+            } else {
+                deserializer.deserialize_bytes(DigestVisitor)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding() {
+        let digest = crate::hash(&[BlsScalar::from(42u64)]);
+
+        let bytes = digest.to_bytes();
+        assert_eq!(HadesDigest::from_bytes(&bytes), Ok(digest));
+
+        // The largest canonical encoding is the one of `p - 1`
+        let max = (-BlsScalar::one()).to_bytes();
+        let mut bytes = max;
+        bytes[0] += 1;
+        assert_eq!(HadesDigest::from_bytes(&bytes), Err(Error::InvalidEncoding));
+        assert!(HadesDigest::from_bytes(&max).is_ok());
+        assert!(HadesDigest::from_bytes(&[0xff; 32]).is_err());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn hex() {
+        let digest = crate::hash(&[BlsScalar::from(42u64)]);
+
+        let hex = alloc::format!("{}", digest);
+        assert_eq!(hex.len(), 64);
+        assert_eq!(hex.parse(), Ok(digest));
+        assert_eq!(hex.to_uppercase().parse(), Ok(digest));
+
+        assert!(hex[1..].parse::<HadesDigest>().is_err());
+        assert!(hex
+            .replacen(&hex[..1], "g", 1)
+            .parse::<HadesDigest>()
+            .is_err());
+        assert!("ff".repeat(32).parse::<HadesDigest>().is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        use serde_test::{
+            assert_de_tokens_error, assert_tokens, Compact, Configure, Readable, Token,
+        };
+
+        const HEX: &str = "2a00000000000000000000000000000000000000000000000000000000000000";
+        const BYTES: [u8; 32] = {
+            let mut bytes = [0u8; 32];
+            bytes[0] = 42;
+            bytes
+        };
+
+        let digest = HadesDigest::from(BlsScalar::from(42u64));
+
+        // Human readable formats use the hex, the others the raw bytes
+        assert_tokens(&digest.readable(), &[Token::Str(HEX)]);
+        assert_tokens(&digest.compact(), &[Token::Bytes(&BYTES)]);
+
+        // Non-canonical encodings are rejected by both
+        assert_de_tokens_error::<Readable<HadesDigest>>(
+            &[Token::Str(
+                "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            )],
+            "the encoding is invalid",
+        );
+        assert_de_tokens_error::<Compact<HadesDigest>>(
+            &[Token::Bytes(&[0xff; 32])],
+            "the encoding is invalid",
+        );
+    }
+
+    #[cfg(feature = "rkyv-impl")]
+    #[test]
+    fn rkyv() {
+        use rkyv::ser::serializers::BufferSerializer;
+        use rkyv::ser::Serializer;
+        use rkyv::AlignedBytes;
+
+        let digest = crate::hash(&[BlsScalar::from(42u64)]);
+
+        let mut serializer = BufferSerializer::new(AlignedBytes([0u8; 64]));
+        let position = serializer
+            .serialize_value(&digest)
+            .expect("The buffer fits the digest");
+        let mut bytes = serializer.into_inner();
+
+        let check = |bytes: &AlignedBytes<64>| {
+            let value = bytes[position..].as_ptr().cast::<ArchivedHadesDigest>();
+            // SAFETY: the buffer is aligned and holds an archived digest at
+            // `position`
+            unsafe { ArchivedHadesDigest::check_bytes(value, &mut ()) }
+        };
+
+        let deserialized: HadesDigest = check(&bytes)
+            .expect("The archived digest is valid")
+            .deserialize(&mut Infallible)
+            .expect("Deserializing a digest is infallible");
+        assert_eq!(deserialized, digest);
+
+        // Non-canonical scalars are rejected by the validation
+        bytes[position..position + HadesDigest::SIZE].fill(0xff);
+        assert!(matches!(check(&bytes), Err(Error::InvalidEncoding)));
+    }
+}
@@ -0,0 +1,136 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Implementation of the `digest` traits over the byte encoding.
+
+use digest::typenum::U32;
+use digest::{FixedOutput, FixedOutputReset, HashMarker, Output, OutputSizeUser, Reset, Update};
+use dusk_bls12_381::BlsScalar;
+
+use crate::bytes::{limb, LIMB_BYTES};
+use crate::sponge::{Sponge, DOMAIN_BYTES};
+use crate::HadesDigest;
+
+/// Hasher streaming bytes into the sponge, matching
+/// [`hash_bytes`](crate::hash_bytes).
+///
+/// The bytes are absorbed a limb at a time, and the output is the
+/// canonical 32-byte encoding of the resulting scalar.
+#[derive(Debug, Clone, Copy)]
+pub struct HadesHasher {
+    sponge: Sponge,
+    buffer: [u8; LIMB_BYTES],
+    buffered: usize,
+    len: u64,
+}
+
+impl Default for HadesHasher {
+    fn default() -> Self {
+        Self {
+            sponge: Sponge::with_domain(DOMAIN_BYTES),
+            buffer: [0u8; LIMB_BYTES],
+            buffered: 0,
+            len: 0,
+        }
+    }
+}
+
+impl HadesHasher {
+    /// Create a new hasher.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Consume the hasher, returning the hash as a digest.
+    pub fn finalize_digest(mut self) -> HadesDigest {
+        if self.buffered > 0 {
+            self.sponge.absorb(&[limb(&self.buffer[..self.buffered])]);
+        }
+        self.sponge.absorb(&[BlsScalar::from(self.len)]);
+
+        self.sponge.finalize().into()
+    }
+}
+
+impl HashMarker for HadesHasher {}
+
+impl OutputSizeUser for HadesHasher {
+    type OutputSize = U32;
+}
+
+impl Update for HadesHasher {
+    fn update(&mut self, mut data: &[u8]) {
+        self.len += data.len() as u64;
+
+        while !data.is_empty() {
+            // Absorb a limb only once more bytes follow, so the last limb is
+            // absorbed on finalization with its padding
+            if self.buffered == LIMB_BYTES {
+                self.sponge.absorb(&[limb(&self.buffer)]);
+                self.buffered = 0;
+            }
+
+            let n = (LIMB_BYTES - self.buffered).min(data.len());
+            self.buffer[self.buffered..self.buffered + n].copy_from_slice(&data[..n]);
+            self.buffered += n;
+            data = &data[n..];
+        }
+    }
+}
+
+impl FixedOutput for HadesHasher {
+    fn finalize_into(self, out: &mut Output<Self>) {
+        out.copy_from_slice(&self.finalize_digest().to_bytes());
+    }
+}
+
+impl Reset for HadesHasher {
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+impl FixedOutputReset for HadesHasher {
+    fn finalize_into_reset(&mut self, out: &mut Output<Self>) {
+        let hasher = core::mem::take(self);
+        hasher.finalize_into(out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash_bytes;
+    use digest::Digest;
+
+    #[test]
+    fn hasher() {
+        let input = [0xab; 100];
+
+        // Split the input at every possible position, across limbs
+        for split in 0..input.len() {
+            let mut hasher = HadesHasher::new();
+            Update::update(&mut hasher, &input[..split]);
+            Update::update(&mut hasher, &input[split..]);
+
+            assert_eq!(hasher.finalize_digest(), hash_bytes(&input));
+        }
+
+        let output = <HadesHasher as Digest>::digest(b"hades");
+        assert_eq!(output.as_slice(), &hash_bytes(b"hades").to_bytes());
+
+        let mut hasher = HadesHasher::new();
+        Update::update(&mut hasher, &[0u8; 31]);
+        assert_eq!(
+            hasher.finalize_fixed_reset().as_slice(),
+            &hash_bytes(&[0u8; 31]).to_bytes()
+        );
+        assert_eq!(
+            hasher.finalize_fixed().as_slice(),
+            &hash_bytes(b"").to_bytes()
+        );
+    }
+}
@@ -0,0 +1,399 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Hashing of JubJub points, and into the JubJub scalar field and curve.
+//!
+//! The JubJub scalar modulus is smaller than the BLS12-381 scalar modulus,
+//! so the output of the hash is truncated to its low [`TRUNCATED_BITS`]
+//! bits, which are always smaller than the JubJub modulus.
+//!
+//! Points are hashed through their affine coordinates `(u, v)`, which are
+//! elements of the BLS12-381 scalar field, in the [`DOMAIN_POINTS`] domain
+//! of the sponge.
+
+use dusk_bls12_381::BlsScalar;
+use dusk_jubjub::{JubJubAffine, JubJubScalar};
+
+use crate::sponge::{Sponge, DOMAIN_POINTS};
+use crate::{hash, u64_from_buffer, HadesDigest};
+
+#[cfg(feature = "plonk")]
+use crate::constraints;
+#[cfg(feature = "plonk")]
+use crate::{hash_gadget, SpongeGadget};
+#[cfg(feature = "plonk")]
+use dusk_plonk::prelude::*;
+
+/// Hashing onto the curve with Elligator 2
+mod hash_to_curve;
+
+pub use hash_to_curve::{hash_to_curve, map_to_curve};
+
+#[cfg(feature = "plonk")]
+pub use hash_to_curve::{hash_to_curve_gadget, map_to_curve_gadget};
+
+/// Amount of bits kept when truncating a hash.
+pub const TRUNCATED_BITS: usize = 250;
+
+/// Amount of bits of the BLS12-381 scalar modulus above the truncated ones.
+#[cfg(feature = "plonk")]
+const HIGH_BITS: usize = 5;
+
+/// Bits of the BLS12-381 scalar modulus above the truncated ones.
+#[cfg(feature = "plonk")]
+const MODULUS_HIGH: u64 = 28;
+
+/// Hash an arbitrary amount of elements into a JubJub scalar, keeping the
+/// low [`TRUNCATED_BITS`] bits of [`hash`].
+pub fn hash_truncated(input: &[BlsScalar]) -> JubJubScalar {
+    truncate(hash(input).as_scalar())
+}
+
+/// Keep the low [`TRUNCATED_BITS`] bits of a scalar.
+pub(crate) fn truncate(scalar: &BlsScalar) -> JubJubScalar {
+    let (low, _) = split(scalar);
+    JubJubScalar::from_raw(low)
+}
+
+/// Hash the given points, in order.
+///
+/// Both affine and extended points are accepted, and the same point hashes
+/// to the same digest in either representation.
+pub fn hash_points<P>(points: &[P]) -> HadesDigest
+where
+    P: Copy + Into<JubJubAffine>,
+{
+    let mut sponge = Sponge::with_domain(DOMAIN_POINTS);
+
+    points.iter().for_each(|point| {
+        let point: JubJubAffine = (*point).into();
+        sponge.absorb(&[point.get_u(), point.get_v()]);
+    });
+
+    sponge.finalize().into()
+}
+
+/// Hash the given points inside a plonk circuit, matching [`hash_points`].
+#[cfg(feature = "plonk")]
+pub fn hash_points_gadget(composer: &mut Composer, points: &[WitnessPoint]) -> Witness {
+    let mut sponge = SpongeGadget::with_domain(composer, DOMAIN_POINTS);
+
+    points.iter().for_each(|point| {
+        sponge.absorb(composer, &[*point.x(), *point.y()]);
+    });
+
+    sponge.finalize(composer)
+}
+
+/// Split the canonical representation of a scalar into its low
+/// [`TRUNCATED_BITS`] bits and the bits above them.
+fn split(scalar: &BlsScalar) -> ([u64; 4], u64) {
+    let bytes = scalar.to_bytes();
+    let mut limbs = [0u64; 4];
+    limbs
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, limb)| *limb = u64_from_buffer(&bytes, 8 * i));
+
+    let high = limbs[3] >> 58;
+    limbs[3] &= (1 << 58) - 1;
+
+    (limbs, high)
+}
+
+/// Hash an arbitrary amount of witnesses into a JubJub scalar, matching
+/// [`hash_truncated`].
+///
+/// The returned witness holds the truncated hash, and can be used as a
+/// JubJub scalar by the curve components of the composer.
+#[cfg(feature = "plonk")]
+pub fn hash_truncated_gadget(composer: &mut Composer, input: &[Witness]) -> Witness {
+    let hash = hash_gadget(composer, input);
+    truncate_gadget(composer, hash)
+}
+
+/// Keep the low [`TRUNCATED_BITS`] bits of `value` inside a plonk circuit,
+/// matching [`truncate`].
+#[cfg(feature = "plonk")]
+pub(crate) fn truncate_gadget(composer: &mut Composer, value: Witness) -> Witness {
+    let (low, high) = split(&composer[value]);
+    let low = BlsScalar::from_raw(low);
+    let high = BlsScalar::from(high);
+
+    let (low, _) = decompose(composer, value, low, high);
+    low
+}
+
+/// Bits of the canonical representation of a scalar, as returned by
+/// [`canonical_bits_gadget`].
+#[cfg(feature = "plonk")]
+type CanonicalBits = [Witness; TRUNCATED_BITS + HIGH_BITS];
+
+/// Decompose `value` into the bits of its canonical representation inside
+/// a plonk circuit, least significant first.
+#[cfg(feature = "plonk")]
+pub(crate) fn canonical_bits_gadget(composer: &mut Composer, value: Witness) -> CanonicalBits {
+    let (low, high) = split(&composer[value]);
+    let low = BlsScalar::from_raw(low);
+    let high = BlsScalar::from(high);
+
+    let (_, bits) = decompose(composer, value, low, high);
+    bits
+}
+
+/// Constrain `low` and `high` to be the canonical decomposition of `value`
+/// into its low [`TRUNCATED_BITS`] bits and the bits above them, returning
+/// the witness of the low bits and the bits of the whole decomposition,
+/// least significant first.
+///
+/// Both parts are decomposed into bits, which also constrains them to their
+/// amount of bits.
+///
+/// Decomposing `value + p` instead of `value` would satisfy the
+/// recomposition, so the decomposition is also constrained to be smaller
+/// than the modulus `p`: the high bits are at most the ones of the modulus,
+/// and when they are equal the low bits are smaller than the ones of the
+/// modulus.
+#[cfg(feature = "plonk")]
+fn decompose(
+    composer: &mut Composer,
+    value: Witness,
+    low: BlsScalar,
+    high: BlsScalar,
+) -> (Witness, CanonicalBits) {
+    let two_pow = BlsScalar::from_raw([0, 0, 0, 1 << 58]);
+
+    let low = composer.append_witness(low);
+    let high = composer.append_witness(high);
+
+    let mut bits = [Composer::ZERO; TRUNCATED_BITS + HIGH_BITS];
+    bits[..TRUNCATED_BITS]
+        .copy_from_slice(&composer.component_decomposition::<TRUNCATED_BITS>(low));
+    bits[TRUNCATED_BITS..].copy_from_slice(&composer.component_decomposition::<HIGH_BITS>(high));
+
+    let constraint = Constraint::new().left(1).a(low).right(two_pow).b(high);
+    let recomposed = composer.gate_add(constraint);
+    composer.assert_equal(recomposed, value);
+
+    // high <= MODULUS_HIGH
+    let constraint = Constraint::new()
+        .left(-BlsScalar::one())
+        .a(high)
+        .constant(BlsScalar::from(MODULUS_HIGH));
+    let margin = composer.gate_add(constraint);
+    constraints::range::<HIGH_BITS>(composer, margin);
+
+    // high == MODULUS_HIGH => low < p - MODULUS_HIGH · 2^250
+    let constraint = Constraint::new()
+        .left(1)
+        .a(high)
+        .constant(-BlsScalar::from(MODULUS_HIGH));
+    let distance = composer.gate_add(constraint);
+    let is_high = constraints::is_zero(composer, distance);
+
+    let modulus_low = -BlsScalar::from(MODULUS_HIGH) * two_pow;
+    let constraint = Constraint::new()
+        .left(-BlsScalar::one())
+        .a(low)
+        .constant(modulus_low - BlsScalar::one());
+    let margin = composer.gate_add(constraint);
+
+    let constraint = Constraint::new().mult(1).a(margin).b(is_high);
+    let margin = composer.gate_mul(constraint);
+    constraints::range::<TRUNCATED_BITS>(composer, margin);
+
+    (low, bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncated() {
+        let input = [BlsScalar::from(42u64), BlsScalar::from(7u64)];
+
+        let digest = hash(&input).to_bytes();
+        let truncated = hash_truncated(&input).to_bytes();
+
+        assert_eq!(truncated[..31], digest[..31]);
+        assert_eq!(truncated[31], digest[31] & 0b11);
+
+        // The largest value keeps all its low bits
+        let (low, high) = split(&-BlsScalar::one());
+        assert_eq!(high, 28);
+        assert_eq!(
+            BlsScalar::from_raw(low) + BlsScalar::from_raw([0, 0, 0, 28 << 58]),
+            -BlsScalar::one()
+        );
+    }
+
+    #[test]
+    fn points() {
+        let a = dusk_jubjub::GENERATOR_EXTENDED * JubJubScalar::from(42u64);
+        let b = dusk_jubjub::GENERATOR_EXTENDED;
+
+        let extended = hash_points(&[a, b]);
+        let affine = hash_points(&[JubJubAffine::from(a), JubJubAffine::from(b)]);
+
+        assert_eq!(extended, affine);
+        assert_ne!(extended, hash_points(&[b, a]));
+        assert_ne!(hash_points::<JubJubAffine>(&[]), hash(&[]));
+
+        let a = JubJubAffine::from(a);
+        assert_ne!(hash_points(&[a]), hash(&[a.get_u(), a.get_v()]));
+    }
+
+    #[cfg(feature = "plonk")]
+    mod gadget {
+        use super::*;
+        use dusk_plonk::prelude::Error;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        #[derive(Default)]
+        struct TruncatedCircuit {
+            input: [BlsScalar; 2],
+            truncated: BlsScalar,
+        }
+
+        impl Circuit for TruncatedCircuit {
+            fn circuit(&self, composer: &mut Composer) -> Result<(), Error> {
+                let input = self.input.map(|x| composer.append_witness(x));
+                let truncated = hash_truncated_gadget(composer, &input);
+
+                let public = composer.append_public(self.truncated);
+                composer.assert_equal(truncated, public);
+
+                Ok(())
+            }
+        }
+
+        struct PointsCircuit {
+            points: [JubJubAffine; 2],
+            hash: BlsScalar,
+        }
+
+        impl Default for PointsCircuit {
+            fn default() -> Self {
+                Self {
+                    points: [JubJubAffine::identity(); 2],
+                    hash: BlsScalar::zero(),
+                }
+            }
+        }
+
+        impl Circuit for PointsCircuit {
+            fn circuit(&self, composer: &mut Composer) -> Result<(), Error> {
+                let points = self.points.map(|point| composer.append_point(point));
+                let hash = hash_points_gadget(composer, &points);
+
+                let public = composer.append_public(self.hash);
+                composer.assert_equal(hash, public);
+
+                Ok(())
+            }
+        }
+
+        /// Circuit decomposing a value with the given low and high bits.
+        #[derive(Default)]
+        struct DecompositionCircuit {
+            value: BlsScalar,
+            low: BlsScalar,
+            high: BlsScalar,
+        }
+
+        impl Circuit for DecompositionCircuit {
+            fn circuit(&self, composer: &mut Composer) -> Result<(), Error> {
+                let value = composer.append_witness(self.value);
+                let (low, _) = decompose(composer, value, self.low, self.high);
+
+                let public = composer.append_public(self.low);
+                composer.assert_equal(low, public);
+
+                Ok(())
+            }
+        }
+
+        #[test]
+        fn hash_truncated() -> Result<(), Error> {
+            let mut rng = StdRng::seed_from_u64(0xbeef);
+
+            let pp = PublicParameters::setup(1 << 12, &mut rng)?;
+            let (prover, verifier) = Compiler::compile::<TruncatedCircuit>(&pp, b"hash_truncated")?;
+
+            let input = [BlsScalar::from(42u64), BlsScalar::from(7u64)];
+            let truncated = super::hash_truncated(&input);
+
+            let circuit = TruncatedCircuit {
+                input,
+                truncated: BlsScalar::from_bytes(&truncated.to_bytes()).unwrap(),
+            };
+            let (proof, public_inputs) = prover.prove(&mut rng, &circuit)?;
+            verifier.verify(&proof, &public_inputs)?;
+
+            Ok(())
+        }
+
+        #[test]
+        fn hash_points() -> Result<(), Error> {
+            let mut rng = StdRng::seed_from_u64(0xbeef);
+
+            let pp = PublicParameters::setup(1 << 11, &mut rng)?;
+            let (prover, verifier) = Compiler::compile::<PointsCircuit>(&pp, b"hash_points")?;
+
+            let points = [
+                JubJubAffine::from(dusk_jubjub::GENERATOR_EXTENDED * JubJubScalar::from(42u64)),
+                dusk_jubjub::GENERATOR,
+            ];
+
+            let circuit = PointsCircuit {
+                points,
+                hash: super::hash_points(&points).into(),
+            };
+            let (proof, public_inputs) = prover.prove(&mut rng, &circuit)?;
+            verifier.verify(&proof, &public_inputs)?;
+
+            let circuit = PointsCircuit {
+                points: [points[1], points[0]],
+                ..circuit
+            };
+            assert!(prover.prove(&mut rng, &circuit).is_err());
+
+            Ok(())
+        }
+
+        #[test]
+        fn non_canonical() -> Result<(), Error> {
+            let mut rng = StdRng::seed_from_u64(0xbeef);
+
+            let pp = PublicParameters::setup(1 << 11, &mut rng)?;
+            let (prover, verifier) = Compiler::compile::<DecompositionCircuit>(&pp, b"truncate")?;
+
+            let value = BlsScalar::from(5u64);
+
+            let circuit = DecompositionCircuit {
+                value,
+                low: value,
+                high: BlsScalar::zero(),
+            };
+            let (proof, public_inputs) = prover.prove(&mut rng, &circuit)?;
+            verifier.verify(&proof, &public_inputs)?;
+
+            // `value + p` recomposes to the same value, but isn't canonical
+            let modulus_low =
+                -BlsScalar::from(MODULUS_HIGH) * BlsScalar::from_raw([0, 0, 0, 1 << 58]);
+            let circuit = DecompositionCircuit {
+                value,
+                low: modulus_low + value,
+                high: BlsScalar::from(MODULUS_HIGH),
+            };
+            assert!(prover.prove(&mut rng, &circuit).is_err());
+
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,469 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Hashing onto the JubJub curve.
+//!
+//! Follows the random oracle construction of RFC 9380: the input is hashed
+//! into two field elements with the sponge, each of them is mapped onto the
+//! curve, and the sum of both points is multiplied by the cofactor.
+//!
+//! The map is Elligator 2 on the Montgomery form `K·t² = s³ + J·s² + s` of
+//! the curve, with `J = 2(a + d)/(a - d)` and `K = 4/(a - d)`, followed by
+//! the rational map onto the twisted Edwards form. It runs in constant
+//! time.
+
+use dusk_bls12_381::BlsScalar;
+use dusk_jubjub::{JubJubAffine, JubJubExtended, EDWARDS_D};
+use ff::Field;
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+use crate::sponge::{Sponge, DOMAIN_HASH_TO_CURVE};
+
+#[cfg(feature = "plonk")]
+use super::canonical_bits_gadget;
+#[cfg(feature = "plonk")]
+use crate::{constraints, SpongeGadget};
+#[cfg(feature = "plonk")]
+use dusk_plonk::prelude::*;
+
+/// Non-square of the scalar field used by the map.
+const Z: u64 = 5;
+
+/// Hash an arbitrary amount of elements onto a point of the prime order
+/// subgroup of JubJub.
+pub fn hash_to_curve(input: &[BlsScalar]) -> JubJubExtended {
+    let mut sponge = Sponge::with_domain(DOMAIN_HASH_TO_CURVE);
+    sponge.absorb(input);
+
+    let u0 = sponge.squeeze();
+    let u1 = sponge.squeeze();
+
+    (map_to_curve(&u0) + map_to_curve(&u1)).mul_by_cofactor()
+}
+
+/// Map a field element onto a point of JubJub with Elligator 2.
+///
+/// The point is not necessarily in the prime order subgroup, and the
+/// distribution of the points is not uniform: use [`hash_to_curve`] unless
+/// the map itself is needed.
+pub fn map_to_curve(u: &BlsScalar) -> JubJubExtended {
+    let zero = BlsScalar::zero();
+    let one = BlsScalar::one();
+
+    let (_, k, _) = montgomery();
+    let (x, y, _) = elligator(u);
+
+    let s = x * k;
+    let t = y * k;
+
+    let exceptional = t.ct_eq(&zero) | (s + one).ct_eq(&zero);
+    let u = BlsScalar::conditional_select(&(s * inv0(&t)), &zero, exceptional);
+    let v = BlsScalar::conditional_select(&((s - one) * inv0(&(s + one))), &one, exceptional);
+
+    JubJubAffine::from_raw_unchecked(u, v).into()
+}
+
+/// `J/K`, `K` and `1/K²` of the Montgomery form, for `a = -1`.
+fn montgomery() -> (BlsScalar, BlsScalar, BlsScalar) {
+    let one = BlsScalar::one();
+
+    let j_k = (EDWARDS_D - one) * inv0(&BlsScalar::from(2u64));
+    let k = -BlsScalar::from(4u64) * inv0(&(one + EDWARDS_D));
+    let k2_inv = (one + EDWARDS_D).square() * inv0(&BlsScalar::from(16u64));
+
+    (j_k, k, k2_inv)
+}
+
+/// Elligator 2 onto the curve `y² = x³ + (J/K)·x² + x/K²`, returning the
+/// point and whether it was taken from the first candidate.
+fn elligator(u: &BlsScalar) -> (BlsScalar, BlsScalar, Choice) {
+    let zero = BlsScalar::zero();
+    let one = BlsScalar::one();
+
+    let (j_k, _, k2_inv) = montgomery();
+    let g = |x: &BlsScalar| x.square() * x + j_k * x.square() + k2_inv * x;
+
+    let x1 = -j_k * inv0(&(one + BlsScalar::from(Z) * u.square()));
+    let x1 = BlsScalar::conditional_select(&x1, &-j_k, x1.ct_eq(&zero));
+    let x2 = -x1 - j_k;
+
+    let y1 = g(&x1).sqrt();
+    let y2 = g(&x2).sqrt();
+
+    // Exactly one of g(x1) and g(x2) is a square
+    let is_square = y1.is_some();
+    let x = BlsScalar::conditional_select(&x2, &x1, is_square);
+    let y = BlsScalar::conditional_select(&y2.unwrap_or(zero), &y1.unwrap_or(zero), is_square);
+
+    // The sign of y is odd for x1 and even for x2
+    let y = BlsScalar::conditional_select(&y, &-y, sgn0(&y) ^ is_square);
+
+    (x, y, is_square)
+}
+
+/// Hash an arbitrary amount of witnesses onto a point of the prime order
+/// subgroup of JubJub inside a plonk circuit, matching [`hash_to_curve`].
+#[cfg(feature = "plonk")]
+pub fn hash_to_curve_gadget(composer: &mut Composer, input: &[Witness]) -> WitnessPoint {
+    let mut sponge = SpongeGadget::with_domain(composer, DOMAIN_HASH_TO_CURVE);
+    sponge.absorb(composer, input);
+
+    let u0 = sponge.squeeze(composer);
+    let u1 = sponge.squeeze(composer);
+
+    let p0 = map_to_curve_gadget(composer, u0);
+    let p1 = map_to_curve_gadget(composer, u1);
+    let point = composer.component_add_point(p0, p1);
+
+    // Multiply by the cofactor 8
+    (0..3).fold(point, |point, _| composer.component_add_point(point, point))
+}
+
+/// Map a witness onto a point of JubJub inside a plonk circuit, matching
+/// [`map_to_curve`].
+///
+/// The prover supplies which of the two candidates lies on the curve,
+/// together with the square root of its image. Since exactly one of the
+/// candidates is a square for every input, the circuit only checks the
+/// root and its sign, which requires the canonical decomposition of the
+/// root.
+#[cfg(feature = "plonk")]
+pub fn map_to_curve_gadget(composer: &mut Composer, u: Witness) -> WitnessPoint {
+    let one = BlsScalar::one();
+    let (j_k, k, k2_inv) = montgomery();
+    let (_, y, is_square) = elligator(&composer[u]);
+    let point = JubJubAffine::from(map_to_curve(&composer[u]));
+
+    // x1 · (1 + Z·u²) = -J/K, where 1 + Z·u² is never zero since -1/Z is
+    // not a square
+    let constraint = Constraint::new().mult(1).a(u).b(u);
+    let u2 = composer.gate_mul(constraint);
+
+    let constraint = Constraint::new().left(Z).a(u2).constant(one);
+    let den = composer.gate_add(constraint);
+
+    let x1 = -j_k * inv0(&composer[den]);
+    let x1 = composer.append_witness(x1);
+    let constraint = Constraint::new().mult(1).a(x1).b(den).constant(j_k);
+    composer.append_gate(constraint);
+
+    let constraint = Constraint::new().left(-one).a(x1).constant(-j_k);
+    let x2 = composer.gate_add(constraint);
+
+    let gx1 = g_gadget(composer, x1, j_k, k2_inv);
+    let gx2 = g_gadget(composer, x2, j_k, k2_inv);
+
+    let is_square = composer.append_witness(BlsScalar::from(is_square.unwrap_u8() as u64));
+    composer.component_boolean(is_square);
+
+    let x = composer.component_select(is_square, x1, x2);
+    let gx = composer.component_select(is_square, gx1, gx2);
+
+    // y² = g(x), with y odd for x1 and even for x2
+    let y = composer.append_witness(y);
+    let constraint = Constraint::new().mult(1).a(y).b(y);
+    let y2 = composer.gate_mul(constraint);
+    composer.assert_equal(y2, gx);
+
+    let bits = canonical_bits_gadget(composer, y);
+    composer.assert_equal(bits[0], is_square);
+
+    let constraint = Constraint::new().left(k).a(x);
+    let s = composer.gate_add(constraint);
+    let constraint = Constraint::new().left(k).a(y);
+    let t = composer.gate_add(constraint);
+
+    let constraint = Constraint::new().left(1).a(s).constant(one);
+    let s_plus = composer.gate_add(constraint);
+    let constraint = Constraint::new().left(1).a(s).constant(-one);
+    let s_minus = composer.gate_add(constraint);
+
+    // The exceptional cases t = 0 and s = -1 map to the identity
+    let t_zero = constraints::is_zero(composer, t);
+    let s_zero = constraints::is_zero(composer, s_plus);
+
+    let constraint = Constraint::new().mult(1).a(t_zero).b(s_zero);
+    let both = composer.gate_mul(constraint);
+    let constraint = Constraint::new()
+        .left(1)
+        .a(t_zero)
+        .right(1)
+        .b(s_zero)
+        .fourth(-one)
+        .d(both);
+    let exceptional = composer.gate_add(constraint);
+
+    // u · t = s and v · (s + 1) = s - 1, with non-zero denominators
+    let unit = composer.append_constant(one);
+
+    let den = composer.component_select(exceptional, unit, t);
+    let num = composer.component_select(exceptional, Composer::ZERO, s);
+    let point = composer.append_point(point);
+
+    let constraint = Constraint::new().mult(1).a(*point.x()).b(den);
+    let product = composer.gate_mul(constraint);
+    composer.assert_equal(product, num);
+
+    let den = composer.component_select(exceptional, unit, s_plus);
+    let num = composer.component_select(exceptional, unit, s_minus);
+
+    let constraint = Constraint::new().mult(1).a(*point.y()).b(den);
+    let product = composer.gate_mul(constraint);
+    composer.assert_equal(product, num);
+
+    point
+}
+
+/// Compute `x³ + (J/K)·x² + x/K²` inside a plonk circuit.
+#[cfg(feature = "plonk")]
+fn g_gadget(composer: &mut Composer, x: Witness, j_k: BlsScalar, k2_inv: BlsScalar) -> Witness {
+    let constraint = Constraint::new().mult(1).a(x).b(x);
+    let x2 = composer.gate_mul(constraint);
+
+    let constraint = Constraint::new()
+        .left(1)
+        .a(x2)
+        .right(j_k)
+        .b(x)
+        .constant(k2_inv);
+    let inner = composer.gate_add(constraint);
+
+    let constraint = Constraint::new().mult(1).a(x).b(inner);
+    composer.gate_mul(constraint)
+}
+
+/// Inverse of `x`, or zero if `x` is zero.
+fn inv0(x: &BlsScalar) -> BlsScalar {
+    x.invert().unwrap_or(BlsScalar::zero())
+}
+
+/// Parity of the canonical representation of `x`.
+fn sgn0(x: &BlsScalar) -> Choice {
+    Choice::from(x.to_bytes()[0] & 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HadesDigest;
+
+    fn scalar(hex: &str) -> BlsScalar {
+        *hex.parse::<HadesDigest>().unwrap().as_scalar()
+    }
+
+    fn assert_point(point: JubJubExtended, u: &str, v: &str) {
+        let point = JubJubAffine::from(point);
+
+        assert_eq!(point.get_u(), scalar(u));
+        assert_eq!(point.get_v(), scalar(v));
+    }
+
+    #[test]
+    fn map() {
+        let vectors = [
+            (
+                BlsScalar::zero(),
+                "0000000000000000000000000000000000000000000000000000000000000000",
+                "0100000000000000000000000000000000000000000000000000000000000000",
+            ),
+            (
+                BlsScalar::one(),
+                "e30024dad66ca05a3f73401417f2c2c51e39fb30fddcf7828b557a76c0a94f69",
+                "9d4bd08b3912d9ea122d7a38b02788ba9520e4593f6ace5294102118026e113b",
+            ),
+            (
+                BlsScalar::from(42u64),
+                "d4e6ebbad73b21f0cc3fdb1f942f6aee37b670252b3cbc81f0005154b4a34435",
+                "af2bd690d96d896cf95c3f48af32b891f5e6a8b2ca4685dcbf8bafe43efa7262",
+            ),
+            (
+                -BlsScalar::one(),
+                "e30024dad66ca05a3f73401417f2c2c51e39fb30fddcf7828b557a76c0a94f69",
+                "9d4bd08b3912d9ea122d7a38b02788ba9520e4593f6ace5294102118026e113b",
+            ),
+        ];
+
+        vectors.iter().for_each(|(input, u, v)| {
+            let point = map_to_curve(input);
+
+            assert!(bool::from(JubJubAffine::from(point).is_on_curve()));
+            assert_point(point, u, v);
+        });
+    }
+
+    #[test]
+    fn hash() {
+        let seven = BlsScalar::from(7u64);
+        let vectors: [(&[BlsScalar], &str, &str); 4] = [
+            (
+                &[],
+                "676d8404a18930e69529f01969f9111e1f756f429029edeb6a19f96d1001e724",
+                "5fd74beefe0c8a4e926ce1ca488b78478d48996aa38d07b73f615062f4f76240",
+            ),
+            (
+                &[BlsScalar::zero()],
+                "3f86367e19aeaf8e1a54bd129fe867db76f9f22bed034baaf1eca144f151d51a",
+                "613b46c386e542a297e9e1946596fe210192087e1da9afbaf3a41fd89eb29465",
+            ),
+            (
+                &[
+                    BlsScalar::one(),
+                    BlsScalar::from(2u64),
+                    BlsScalar::from(3u64),
+                ],
+                "0b35181e258d305515c27dc675ac8f72dfc94c0129a5b8138b8af034d7dcca04",
+                "4f9490df7054121f9f97957965105756aae72d1b62b1b826e960195db13c971f",
+            ),
+            (
+                &[seven; 5],
+                "8b627b7305b390359f78915e67864ab4aa76fbdeb3075d7ab54174f88e9d8425",
+                "75ff47fc3925a32121d77bec85455cd29c848f0528d70b54498acbdfcfd9b731",
+            ),
+        ];
+
+        vectors.iter().for_each(|(input, u, v)| {
+            let point = hash_to_curve(input);
+
+            assert!(bool::from(JubJubAffine::from(point).is_on_curve()));
+            assert!(bool::from(point.is_prime_order()));
+            assert_point(point, u, v);
+        });
+    }
+
+    #[test]
+    fn random_inputs() {
+        use rand::rngs::StdRng;
+        use rand::{RngCore, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(0xbeef);
+
+        for _ in 0..64 {
+            let mut bytes = [0u8; 64];
+            rng.fill_bytes(&mut bytes);
+            let u = BlsScalar::from_bytes_wide(&bytes);
+
+            // The map only depends on the square of the input
+            let point = map_to_curve(&u);
+            assert!(bool::from(JubJubAffine::from(point).is_on_curve()));
+            assert_eq!(point, map_to_curve(&-u));
+
+            let point = hash_to_curve(&[u]);
+            assert!(bool::from(JubJubAffine::from(point).is_on_curve()));
+            assert!(bool::from(point.is_prime_order()));
+        }
+    }
+
+    #[cfg(feature = "plonk")]
+    mod gadget {
+        use super::*;
+        use dusk_plonk::prelude::Error;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        struct MapCircuit {
+            u: BlsScalar,
+            point: JubJubAffine,
+        }
+
+        impl Default for MapCircuit {
+            fn default() -> Self {
+                Self {
+                    u: BlsScalar::zero(),
+                    point: JubJubAffine::identity(),
+                }
+            }
+        }
+
+        impl Circuit for MapCircuit {
+            fn circuit(&self, composer: &mut Composer) -> Result<(), Error> {
+                let u = composer.append_witness(self.u);
+                let point = map_to_curve_gadget(composer, u);
+
+                let public = composer.append_public_point(self.point);
+                composer.assert_equal_point(point, public);
+
+                Ok(())
+            }
+        }
+
+        struct HashCircuit {
+            input: [BlsScalar; 2],
+            point: JubJubAffine,
+        }
+
+        impl Default for HashCircuit {
+            fn default() -> Self {
+                Self {
+                    input: [BlsScalar::zero(); 2],
+                    point: JubJubAffine::identity(),
+                }
+            }
+        }
+
+        impl Circuit for HashCircuit {
+            fn circuit(&self, composer: &mut Composer) -> Result<(), Error> {
+                let input = self.input.map(|x| composer.append_witness(x));
+                let point = hash_to_curve_gadget(composer, &input);
+
+                let public = composer.append_public_point(self.point);
+                composer.assert_equal_point(point, public);
+
+                Ok(())
+            }
+        }
+
+        #[test]
+        fn map() -> Result<(), Error> {
+            let mut rng = StdRng::seed_from_u64(0xbeef);
+
+            let pp = PublicParameters::setup(1 << 12, &mut rng)?;
+            let (prover, verifier) = Compiler::compile::<MapCircuit>(&pp, b"map_to_curve")?;
+
+            // Zero hits the exceptional case, and 1 and 42 take a different
+            // candidate
+            for u in [0u64, 1, 42, 1337] {
+                let u = BlsScalar::from(u);
+                let point = JubJubAffine::from(map_to_curve(&u));
+
+                let circuit = MapCircuit { u, point };
+                let (proof, public_inputs) = prover.prove(&mut rng, &circuit)?;
+                verifier.verify(&proof, &public_inputs)?;
+            }
+
+            // The point with the other sign is rejected
+            let u = BlsScalar::from(42u64);
+            let point = -JubJubAffine::from(map_to_curve(&u));
+
+            let circuit = MapCircuit { u, point };
+            assert!(prover.prove(&mut rng, &circuit).is_err());
+
+            Ok(())
+        }
+
+        #[test]
+        fn hash() -> Result<(), Error> {
+            let mut rng = StdRng::seed_from_u64(0xbeef);
+
+            let pp = PublicParameters::setup(1 << 13, &mut rng)?;
+            let (prover, verifier) = Compiler::compile::<HashCircuit>(&pp, b"hash_to_curve")?;
+
+            let input = [BlsScalar::from(42u64), BlsScalar::from(7u64)];
+            let point = hash_to_curve(&input).into();
+
+            let circuit = HashCircuit { input, point };
+            let (proof, public_inputs) = prover.prove(&mut rng, &circuit)?;
+            verifier.verify(&proof, &public_inputs)?;
+
+            let circuit = HashCircuit {
+                input: [input[1], input[0]],
+                point,
+            };
+            assert!(prover.prove(&mut rng, &circuit).is_err());
+
+            Ok(())
+        }
+    }
+}
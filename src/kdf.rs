@@ -0,0 +1,255 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Key derivation in the extract-and-expand style of HKDF.
+//!
+//! The extract stage hashes a salt and the input key material into a
+//! pseudorandom key in the [`DOMAIN_KDF_EXTRACT`] domain of the sponge. The
+//! expand stage absorbs the pseudorandom key and a context label in the
+//! [`DOMAIN_KDF_EXPAND`] domain, and squeezes as many outputs as requested.
+//! Different labels give independent outputs from the same key.
+
+use core::fmt;
+
+use dusk_bls12_381::BlsScalar;
+use dusk_jubjub::JubJubScalar;
+
+use crate::bytes::absorb_bytes;
+use crate::jubjub::truncate;
+use crate::sponge::{Sponge, DOMAIN_KDF_EXPAND, DOMAIN_KDF_EXTRACT};
+
+#[cfg(feature = "plonk")]
+use crate::bytes::absorb_bytes_gadget;
+#[cfg(feature = "plonk")]
+use crate::jubjub::truncate_gadget;
+#[cfg(feature = "plonk")]
+use crate::SpongeGadget;
+#[cfg(feature = "plonk")]
+use dusk_plonk::prelude::*;
+
+/// Key derivation function holding a pseudorandom key.
+#[derive(Clone, Copy)]
+pub struct HadesKdf {
+    prk: BlsScalar,
+}
+
+impl fmt::Debug for HadesKdf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HadesKdf").finish_non_exhaustive()
+    }
+}
+
+impl HadesKdf {
+    /// Extract a pseudorandom key from the input key material `ikm`.
+    ///
+    /// The `salt` may be public, and is zero if none is available.
+    pub fn extract(salt: &BlsScalar, ikm: &[BlsScalar]) -> Self {
+        let mut sponge = Sponge::with_domain(DOMAIN_KDF_EXTRACT);
+        sponge.absorb(&[*salt]);
+        sponge.absorb(ikm);
+
+        Self::from_prk(sponge.finalize())
+    }
+
+    /// Create a KDF from an already extracted pseudorandom key.
+    pub const fn from_prk(prk: BlsScalar) -> Self {
+        Self { prk }
+    }
+
+    /// Pseudorandom key of the KDF.
+    pub const fn prk(&self) -> &BlsScalar {
+        &self.prk
+    }
+
+    /// Fill `out` with the scalars derived for the context `label`.
+    pub fn expand(&self, label: &[u8], out: &mut [BlsScalar]) {
+        let mut sponge = self.sponge(label);
+        out.iter_mut().for_each(|x| *x = sponge.squeeze());
+    }
+
+    /// Fill `out` with the JubJub scalars derived for the context `label`.
+    ///
+    /// Every JubJub scalar is an output of [`HadesKdf::expand`] truncated
+    /// to its low [`TRUNCATED_BITS`](crate::TRUNCATED_BITS) bits.
+    pub fn expand_jubjub(&self, label: &[u8], out: &mut [JubJubScalar]) {
+        let mut sponge = self.sponge(label);
+        out.iter_mut()
+            .for_each(|x| *x = truncate(&sponge.squeeze()));
+    }
+
+    fn sponge(&self, label: &[u8]) -> Sponge {
+        let mut sponge = Sponge::with_domain(DOMAIN_KDF_EXPAND);
+        sponge.absorb(&[self.prk]);
+        absorb_bytes(&mut sponge, label);
+        sponge
+    }
+}
+
+/// Key derivation function inside a plonk circuit, matching [`HadesKdf`].
+///
+/// The labels are constants of the circuit.
+#[cfg(feature = "plonk")]
+#[derive(Debug, Clone, Copy)]
+pub struct KdfGadget {
+    prk: Witness,
+}
+
+#[cfg(feature = "plonk")]
+impl KdfGadget {
+    /// Extract a pseudorandom key from the input key material `ikm`.
+    pub fn extract(composer: &mut Composer, salt: Witness, ikm: &[Witness]) -> Self {
+        let mut sponge = SpongeGadget::with_domain(composer, DOMAIN_KDF_EXTRACT);
+        sponge.absorb(composer, &[salt]);
+        sponge.absorb(composer, ikm);
+
+        Self::from_prk(sponge.finalize(composer))
+    }
+
+    /// Create a KDF from an already extracted pseudorandom key.
+    pub const fn from_prk(prk: Witness) -> Self {
+        Self { prk }
+    }
+
+    /// Pseudorandom key of the KDF.
+    pub const fn prk(&self) -> Witness {
+        self.prk
+    }
+
+    /// Fill `out` with the scalars derived for the context `label`.
+    pub fn expand(&self, composer: &mut Composer, label: &[u8], out: &mut [Witness]) {
+        let mut sponge = self.sponge(composer, label);
+        out.iter_mut().for_each(|x| *x = sponge.squeeze(composer));
+    }
+
+    /// Fill `out` with the JubJub scalars derived for the context `label`.
+    pub fn expand_jubjub(&self, composer: &mut Composer, label: &[u8], out: &mut [Witness]) {
+        let mut sponge = self.sponge(composer, label);
+        out.iter_mut().for_each(|x| {
+            let scalar = sponge.squeeze(composer);
+            *x = truncate_gadget(composer, scalar);
+        });
+    }
+
+    fn sponge(&self, composer: &mut Composer, label: &[u8]) -> SpongeGadget {
+        let mut sponge = SpongeGadget::with_domain(composer, DOMAIN_KDF_EXPAND);
+        sponge.absorb(composer, &[self.prk]);
+        absorb_bytes_gadget(composer, &mut sponge, label);
+        sponge
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: [BlsScalar; 2] = [BlsScalar::one(), BlsScalar::zero()];
+
+    #[test]
+    fn derive() {
+        let kdf = HadesKdf::extract(&BlsScalar::zero(), &SEED);
+
+        let mut view = [BlsScalar::zero(); 6];
+        let mut nullifier = [BlsScalar::zero(); 6];
+        kdf.expand(b"view key", &mut view);
+        kdf.expand(b"nullifier key", &mut nullifier);
+
+        assert!(view.iter().all(|x| !nullifier.contains(x)));
+
+        // A shorter output is a prefix of a longer one
+        let mut prefix = [BlsScalar::zero(); 2];
+        kdf.expand(b"view key", &mut prefix);
+        assert_eq!(prefix, view[..2]);
+
+        // Salt and key material both change the key
+        let salted = HadesKdf::extract(&BlsScalar::one(), &SEED);
+        assert_ne!(salted.prk(), kdf.prk());
+        assert_ne!(
+            HadesKdf::extract(&BlsScalar::zero(), &SEED[..1]).prk(),
+            kdf.prk()
+        );
+
+        let mut jubjub = [JubJubScalar::zero(); 6];
+        kdf.expand_jubjub(b"view key", &mut jubjub);
+        jubjub
+            .iter()
+            .zip(view.iter())
+            .for_each(|(j, v)| assert_eq!(*j, truncate(v)));
+    }
+
+    #[cfg(feature = "plonk")]
+    mod gadget {
+        use super::*;
+        use dusk_plonk::prelude::Error;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        const LABEL: &[u8] = b"note secret";
+
+        #[derive(Default)]
+        struct KdfCircuit {
+            seed: [BlsScalar; 2],
+            scalars: [BlsScalar; 5],
+            jubjub: BlsScalar,
+        }
+
+        impl Circuit for KdfCircuit {
+            fn circuit(&self, composer: &mut Composer) -> Result<(), Error> {
+                let salt = Composer::ZERO;
+                let seed = self.seed.map(|x| composer.append_witness(x));
+
+                let kdf = KdfGadget::extract(composer, salt, &seed);
+
+                let mut scalars = [Composer::ZERO; 5];
+                kdf.expand(composer, LABEL, &mut scalars);
+
+                let mut jubjub = [Composer::ZERO; 1];
+                kdf.expand_jubjub(composer, LABEL, &mut jubjub);
+
+                for (witness, expected) in scalars.iter().zip(self.scalars.iter()) {
+                    let public = composer.append_public(*expected);
+                    composer.assert_equal(*witness, public);
+                }
+
+                let public = composer.append_public(self.jubjub);
+                composer.assert_equal(jubjub[0], public);
+
+                Ok(())
+            }
+        }
+
+        #[test]
+        fn derive() -> Result<(), Error> {
+            let mut rng = StdRng::seed_from_u64(0xbeef);
+
+            let pp = PublicParameters::setup(1 << 13, &mut rng)?;
+            let (prover, verifier) = Compiler::compile::<KdfCircuit>(&pp, b"kdf")?;
+
+            let kdf = HadesKdf::extract(&BlsScalar::zero(), &SEED);
+
+            let mut scalars = [BlsScalar::zero(); 5];
+            kdf.expand(LABEL, &mut scalars);
+
+            let mut jubjub = [JubJubScalar::zero(); 1];
+            kdf.expand_jubjub(LABEL, &mut jubjub);
+
+            let circuit = KdfCircuit {
+                seed: SEED,
+                scalars,
+                jubjub: BlsScalar::from_bytes(&jubjub[0].to_bytes()).unwrap(),
+            };
+            let (proof, public_inputs) = prover.prove(&mut rng, &circuit)?;
+            verifier.verify(&proof, &public_inputs)?;
+
+            let circuit = KdfCircuit {
+                seed: [BlsScalar::one(); 2],
+                ..circuit
+            };
+            assert!(prover.prove(&mut rng, &circuit).is_err());
+
+            Ok(())
+        }
+    }
+}
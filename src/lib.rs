@@ -9,12 +9,75 @@
 #![doc = include_str!("../README.md")]
 #![deprecated = "This crate is deprecated. The code was moved to dusk-poseidon."]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+/// Authenticated encryption with associated data.
+mod aead;
+
+/// Encoding of byte strings into scalars.
+mod bytes;
+
+/// Keyed HadesMiMC block cipher.
+mod cipher;
+
+/// Ready-made plonk circuits, and the keys to prove and verify them.
+#[cfg(feature = "plonk")]
+mod circuits;
+
+/// Hiding commitments to any amount of scalars.
+mod commitment;
+#[cfg(feature = "plonk")]
+mod constraints;
+mod error;
+mod hades_digest;
+
+/// Hasher implementing the traits of the `digest` crate.
+#[cfg(feature = "digest")]
+mod hasher;
+
+/// Hashing of JubJub points, and into the JubJub scalar field and curve.
+mod jubjub;
+
+/// Key derivation in the extract-and-expand style of HKDF.
+mod kdf;
+
+/// Message authentication codes keyed with a secret scalar.
+mod mac;
 mod mds_matrix;
+
+/// Compression of many public inputs into a single one.
+mod public_inputs;
 mod round_constants;
 
+/// Binary Merkle trees hashed with the Hades252 permutation.
+#[cfg(feature = "alloc")]
+mod merkle;
+
+/// Deterministic random number generator on the duplex sponge.
+mod rng;
+
+/// Schnorr signatures over JubJub with challenges hashed by the sponge.
+mod schnorr;
+
+/// Sponge hashing an arbitrary amount of elements.
+mod sponge;
+
 /// Strategies implemented for the Hades252 algorithm.
 mod strategies;
 
+/// Fiat-Shamir transcripts over the duplex sponge.
+mod transcript;
+
+/// Winternitz one-time signatures.
+mod wots;
+
+/// Many-time signatures over a Merkle tree of one-time keys.
+#[cfg(feature = "alloc")]
+mod xmss;
+
 /// Total ammount of full rounds that will be applied.
 /// This is expressed as `RF` in the paper.
 pub const TOTAL_FULL_ROUNDS: usize = 8;
@@ -30,6 +93,93 @@ pub const WIDTH: usize = 5;
 pub use strategies::GadgetStrategy;
 pub use strategies::{ScalarStrategy, Strategy};
 
+pub use error::Error;
+pub use hades_digest::HadesDigest;
+
+pub use aead::{HadesAead, Nonce};
+
+#[cfg(feature = "alloc")]
+pub use sponge::batch_hash;
+pub use sponge::{
+    hash, Sponge, DOMAIN_AEAD, DOMAIN_BYTES, DOMAIN_COMMITMENT, DOMAIN_HASH, DOMAIN_HASH_TO_CURVE,
+    DOMAIN_KDF_EXPAND, DOMAIN_KDF_EXTRACT, DOMAIN_MAC, DOMAIN_POINTS, DOMAIN_PUBLIC_INPUTS,
+    DOMAIN_RNG, DOMAIN_SCHNORR, DOMAIN_TRANSCRIPT, DOMAIN_WOTS, DOMAIN_WOTS_KEY, RATE,
+};
+#[cfg(feature = "plonk")]
+pub use sponge::{hash_gadget, hash_variable_gadget, SpongeGadget};
+
+#[cfg(feature = "plonk")]
+pub use bytes::hash_bytes_gadget;
+
+#[cfg(feature = "alloc")]
+pub use bytes::{decode_bytes, encode_bytes};
+pub use bytes::{hash_bytes, LIMB_BYTES};
+#[cfg(feature = "digest")]
+pub use hasher::HadesHasher;
+
+#[cfg(feature = "plonk")]
+pub use cipher::cipher_gadget;
+pub use cipher::HadesCipher;
+
+#[cfg(all(feature = "std", feature = "plonk"))]
+pub use circuits::read_verifier;
+#[cfg(feature = "plonk")]
+pub use circuits::{CircuitKeys, MembershipCircuit, PreimageCircuit};
+
+#[cfg(feature = "plonk")]
+pub use commitment::commitment_gadget;
+pub use commitment::Commitment;
+
+pub use jubjub::{hash_points, hash_to_curve, hash_truncated, map_to_curve, TRUNCATED_BITS};
+#[cfg(feature = "plonk")]
+pub use jubjub::{
+    hash_points_gadget, hash_to_curve_gadget, hash_truncated_gadget, map_to_curve_gadget,
+};
+
+pub use kdf::HadesKdf;
+#[cfg(feature = "plonk")]
+pub use kdf::KdfGadget;
+
+#[cfg(feature = "plonk")]
+pub use mac::mac_gadget;
+pub use mac::HadesMac;
+
+pub use public_inputs::compress_public_inputs;
+#[cfg(feature = "plonk")]
+pub use public_inputs::{compress_public_inputs_gadget, Compressed, CompressedCircuit};
+
+pub use rng::{HadesRng, BYTES_PER_SCALAR};
+
+#[cfg(feature = "plonk")]
+pub use schnorr::schnorr_gadget;
+pub use schnorr::{SchnorrPublicKey, SchnorrSecretKey, SchnorrSignature};
+
+pub use transcript::HadesTranscript;
+#[cfg(feature = "plonk")]
+pub use transcript::TranscriptGadget;
+
+#[cfg(feature = "plonk")]
+pub use wots::wots_gadget;
+pub use wots::{WotsPublicKey, WotsSecretKey, WotsSignature, WINTERNITZ, WOTS_CHAINS};
+
+#[cfg(feature = "plonk")]
+pub use xmss::xmss_gadget;
+#[cfg(feature = "alloc")]
+pub use xmss::{XmssPublicKey, XmssSecretKey, XmssSignature};
+
+#[cfg(feature = "std")]
+pub use merkle::FileStore;
+#[cfg(feature = "alloc")]
+pub use merkle::{
+    bag_peaks, empty_subtrees, hash_node, ConsistencyProof, IncrementalTree, IndexedLeaf,
+    IndexedTree, MemoryStore, MerkleTree, Mmr, MmrProof, MultiProof, NodeStore, NonMembership,
+    Opening, VALUE_BITS,
+};
+#[cfg(feature = "plonk")]
+pub use merkle::{
+    hash_node_gadget, mmr_gadget, non_membership_gadget, opening_gadget, update_gadget,
+};
+
 pub(crate) const fn u64_from_buffer<const N: usize>(buf: &[u8; N], i: usize) -> u64 {
     u64::from_le_bytes([
         buf[i],
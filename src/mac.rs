@@ -0,0 +1,148 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Message authentication codes keyed with a secret scalar.
+//!
+//! The key is absorbed before the message in the [`DOMAIN_MAC`] domain of
+//! the sponge, so a tag can't be computed without the key, and tags never
+//! collide with the hashes of other domains.
+
+use core::fmt;
+
+use dusk_bls12_381::BlsScalar;
+
+use crate::sponge::{Sponge, DOMAIN_MAC};
+use crate::HadesDigest;
+
+#[cfg(feature = "plonk")]
+use crate::SpongeGadget;
+#[cfg(feature = "plonk")]
+use dusk_plonk::prelude::*;
+
+/// Message authentication code keyed with a secret scalar.
+#[derive(Clone, Copy)]
+pub struct HadesMac {
+    key: BlsScalar,
+}
+
+impl fmt::Debug for HadesMac {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HadesMac").finish_non_exhaustive()
+    }
+}
+
+impl HadesMac {
+    /// Create a MAC with the given secret key.
+    pub const fn new(key: BlsScalar) -> Self {
+        Self { key }
+    }
+
+    /// Compute the tag of `message`.
+    pub fn tag(&self, message: &[BlsScalar]) -> HadesDigest {
+        let mut sponge = Sponge::with_domain(DOMAIN_MAC);
+        sponge.absorb(&[self.key]);
+        sponge.absorb(message);
+
+        sponge.finalize().into()
+    }
+
+    /// Check that `tag` is the tag of `message`, in constant time.
+    pub fn verify(&self, message: &[BlsScalar], tag: &HadesDigest) -> bool {
+        self.tag(message) == *tag
+    }
+}
+
+/// Compute, inside a plonk circuit, the tag of `message` with the secret
+/// `key`, matching [`HadesMac::tag`].
+///
+/// The returned tag is left unconstrained, so the caller can compare it
+/// with a public input to prove knowledge of a key producing it.
+#[cfg(feature = "plonk")]
+pub fn mac_gadget(composer: &mut Composer, key: Witness, message: &[Witness]) -> Witness {
+    let mut sponge = SpongeGadget::with_domain(composer, DOMAIN_MAC);
+    sponge.absorb(composer, &[key]);
+    sponge.absorb(composer, message);
+
+    sponge.finalize(composer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: [BlsScalar; 3] = [BlsScalar::one(), BlsScalar::one(), BlsScalar::zero()];
+
+    #[test]
+    fn mac() {
+        let mac = HadesMac::new(BlsScalar::from(42u64));
+        let tag = mac.tag(&MESSAGE);
+
+        assert!(mac.verify(&MESSAGE, &tag));
+        assert!(!mac.verify(&MESSAGE[..2], &tag));
+        assert!(!HadesMac::new(BlsScalar::from(43u64)).verify(&MESSAGE, &tag));
+
+        // A tag is not the hash of the key followed by the message
+        let mut input = [BlsScalar::from(42u64); 4];
+        input[1..].copy_from_slice(&MESSAGE);
+        assert_ne!(tag, crate::hash(&input));
+    }
+
+    #[cfg(feature = "plonk")]
+    mod gadget {
+        use super::*;
+        use dusk_plonk::prelude::Error;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        #[derive(Default)]
+        struct MacCircuit {
+            key: BlsScalar,
+            message: [BlsScalar; 3],
+            tag: BlsScalar,
+        }
+
+        impl Circuit for MacCircuit {
+            fn circuit(&self, composer: &mut Composer) -> Result<(), Error> {
+                let key = composer.append_witness(self.key);
+                let message = self.message.map(|x| composer.append_public(x));
+
+                let tag = mac_gadget(composer, key, &message);
+
+                let public = composer.append_public(self.tag);
+                composer.assert_equal(tag, public);
+
+                Ok(())
+            }
+        }
+
+        #[test]
+        fn knowledge_of_key() -> Result<(), Error> {
+            let mut rng = StdRng::seed_from_u64(0xbeef);
+
+            let pp = PublicParameters::setup(1 << 11, &mut rng)?;
+            let (prover, verifier) = Compiler::compile::<MacCircuit>(&pp, b"mac")?;
+
+            let key = BlsScalar::from(42u64);
+            let tag = HadesMac::new(key).tag(&MESSAGE).into();
+
+            let circuit = MacCircuit {
+                key,
+                message: MESSAGE,
+                tag,
+            };
+            let (proof, public_inputs) = prover.prove(&mut rng, &circuit)?;
+            verifier.verify(&proof, &public_inputs)?;
+
+            let circuit = MacCircuit {
+                key: BlsScalar::from(43u64),
+                ..circuit
+            };
+            assert!(prover.prove(&mut rng, &circuit).is_err());
+
+            Ok(())
+        }
+    }
+}
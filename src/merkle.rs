@@ -0,0 +1,652 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Binary Merkle trees whose nodes are hashed with the `Hades252`
+//! permutation.
+//!
+//! A node is computed by permuting `[NODE_CAPACITY, left, right, 0, 0]`
+//! and taking the first element of the rate. Empty leaves are represented
+//! by `BlsScalar::zero()`.
+
+use alloc::vec::Vec;
+use dusk_bls12_381::BlsScalar;
+
+use crate::{Error, HadesDigest, ScalarStrategy, Strategy, WIDTH};
+
+#[cfg(feature = "plonk")]
+use crate::GadgetStrategy;
+#[cfg(feature = "plonk")]
+use dusk_plonk::prelude::*;
+
+/// Append-only Merkle tree storing only its frontier
+mod incremental;
+/// Indexed Merkle tree for non-membership proofs
+mod indexed;
+/// Merkle Mountain Range accumulator
+mod mmr;
+/// Openings of many leaves at once
+mod multiproof;
+/// Storage backends for the nodes of a tree
+mod store;
+
+pub use incremental::IncrementalTree;
+pub use indexed::{IndexedLeaf, IndexedTree, NonMembership, VALUE_BITS};
+pub use mmr::{bag_peaks, ConsistencyProof, Mmr, MmrProof};
+pub use multiproof::MultiProof;
+pub use store::{MemoryStore, NodeStore};
+
+#[cfg(feature = "std")]
+pub use store::FileStore;
+
+#[cfg(feature = "plonk")]
+pub use indexed::non_membership_gadget;
+#[cfg(feature = "plonk")]
+pub use mmr::mmr_gadget;
+
+/// Capacity element used to hash two children into their parent.
+///
+/// Following the Poseidon paper, the capacity of a Merkle tree of arity `a`
+/// is set to `2^a - 1`.
+const NODE_CAPACITY: u64 = (1 << 2) - 1;
+
+/// Hash two children into their parent node.
+pub fn hash_node(left: &BlsScalar, right: &BlsScalar) -> BlsScalar {
+    let mut state = [BlsScalar::zero(); WIDTH];
+
+    state[0] = BlsScalar::from(NODE_CAPACITY);
+    state[1] = *left;
+    state[2] = *right;
+
+    ScalarStrategy::new().perm(&mut state);
+
+    state[1]
+}
+
+/// Compute the roots of the empty subtrees of a tree of the given `depth`.
+///
+/// The element at index `i` is the root of an empty subtree of height `i`,
+/// so the first element is the empty leaf and the last one is the root of
+/// the empty tree.
+pub fn empty_subtrees(depth: usize) -> Vec<BlsScalar> {
+    let mut empty = Vec::with_capacity(depth + 1);
+    let mut node = BlsScalar::zero();
+
+    empty.push(node);
+    for _ in 0..depth {
+        node = hash_node(&node, &node);
+        empty.push(node);
+    }
+
+    empty
+}
+
+/// Binary Merkle tree of height `H`, storing its nodes in `S`.
+///
+/// Leaves are appended from the left and can be updated in place. Only the
+/// nodes covering the occupied leaves are stored, the remaining ones are
+/// taken from the roots of the empty subtrees.
+///
+/// `H` must be at most `64`, since positions are represented as `u64`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleTree<const H: usize, S = MemoryStore> {
+    store: S,
+    empty: Vec<BlsScalar>,
+}
+
+impl<const H: usize> Default for MerkleTree<H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const H: usize> MerkleTree<H> {
+    /// Create a new empty tree held in memory.
+    pub fn new() -> Self {
+        Self::with_store(MemoryStore::new())
+    }
+
+    /// Create a tree in memory holding the given leaves.
+    ///
+    /// See [`MerkleTree::extend`].
+    pub fn from_leaves(leaves: &[BlsScalar]) -> Result<Self, Error> {
+        let mut tree = Self::new();
+        tree.extend(leaves)?;
+        Ok(tree)
+    }
+}
+
+impl<const H: usize, S: NodeStore> MerkleTree<H, S> {
+    /// Open the tree held by the given store.
+    ///
+    /// An empty store yields an empty tree, otherwise the tree is reloaded
+    /// as it was when the store was last committed.
+    pub fn with_store(store: S) -> Self {
+        Self {
+            store,
+            empty: empty_subtrees(H),
+        }
+    }
+
+    /// Store holding the nodes of the tree.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Consume the tree, returning its store.
+    pub fn into_store(self) -> S {
+        self.store
+    }
+
+    /// Persist the changes made to the tree since the last commit.
+    ///
+    /// If an operation failed with an error of the store, the tree may have
+    /// been left half updated, and shouldn't be committed.
+    pub fn commit(&mut self) -> Result<(), S::Error> {
+        self.store.commit()
+    }
+
+    /// Amount of leaves the tree can hold.
+    pub const fn capacity() -> u128 {
+        1u128 << H
+    }
+
+    /// Amount of leaves appended to the tree.
+    pub fn len(&self) -> u64 {
+        self.store.len()
+    }
+
+    /// Return `true` if no leaf was appended to the tree.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Root of the tree.
+    pub fn root(&self) -> Result<HadesDigest, S::Error> {
+        self.node(H, 0).map(HadesDigest::from)
+    }
+
+    /// Leaf at the given position, if it was appended and not pruned.
+    pub fn leaf(&self, position: u64) -> Result<Option<BlsScalar>, S::Error> {
+        self.store.get(0, position)
+    }
+
+    /// Append a leaf to the tree, returning its position.
+    pub fn push(&mut self, leaf: BlsScalar) -> Result<u64, S::Error> {
+        let position = self.len();
+
+        if position as u128 >= Self::capacity() {
+            return Err(Error::TreeFull.into());
+        }
+
+        self.store.set_len(position + 1);
+        self.store.insert(0, position, leaf);
+        self.update_path(position)?;
+
+        Ok(position)
+    }
+
+    /// Append many leaves to the tree at once.
+    ///
+    /// The nodes are recomputed level by level, hashing every affected node
+    /// of a level only once. With the `parallel` feature the nodes of each
+    /// level are hashed on all the cores of the machine. Returns
+    /// [`Error::TreeFull`] and leaves the tree untouched if the leaves don't
+    /// fit in it.
+    pub fn extend(&mut self, leaves: &[BlsScalar]) -> Result<(), S::Error> {
+        let start = self.len();
+        let end = start as u128 + leaves.len() as u128;
+
+        if end > Self::capacity() {
+            return Err(Error::TreeFull.into());
+        }
+        if leaves.is_empty() {
+            return Ok(());
+        }
+
+        self.store.set_len(end as u64);
+        leaves.iter().zip(start..).for_each(|(leaf, position)| {
+            self.store.insert(0, position, *leaf);
+        });
+
+        let (mut first, mut last) = (start, end as u64 - 1);
+        for level in 0..H {
+            first >>= 1;
+            last >>= 1;
+
+            let children = (first..=last)
+                .map(|i| Ok((self.node(level, i << 1)?, self.node(level, (i << 1) | 1)?)))
+                .collect::<Result<Vec<_>, S::Error>>()?;
+
+            hash_level(&children)
+                .into_iter()
+                .zip(first..)
+                .for_each(|(node, index)| self.store.insert(level + 1, index, node));
+        }
+
+        Ok(())
+    }
+
+    /// Replace the leaf at `position`, returning the previous one.
+    ///
+    /// Returns `None` and leaves the tree untouched if no leaf was appended
+    /// at `position`, or if it was pruned.
+    pub fn update(
+        &mut self,
+        position: u64,
+        leaf: BlsScalar,
+    ) -> Result<Option<BlsScalar>, S::Error> {
+        let previous = match self.leaf(position)? {
+            Some(previous) => previous,
+            None => return Ok(None),
+        };
+
+        self.store.insert(0, position, leaf);
+        self.update_path(position)?;
+
+        Ok(Some(previous))
+    }
+
+    /// Authentication path of the leaf at `position`, if it was appended
+    /// and not pruned.
+    pub fn opening(&self, position: u64) -> Result<Option<Opening<H>>, S::Error> {
+        if self.leaf(position)?.is_none() {
+            return Ok(None);
+        }
+
+        let mut branch = [BlsScalar::zero(); H];
+        for (level, sibling) in branch.iter_mut().enumerate() {
+            let index = (position >> level) ^ 1;
+            *sibling = self.node(level, index)?;
+        }
+
+        Ok(Some(Opening::new(branch, position)))
+    }
+
+    /// Remove every node below the node at `level` and `index`, keeping the
+    /// node itself.
+    ///
+    /// The root and the openings of the remaining leaves are unaffected,
+    /// while the leaves below the pruned node can't be opened or updated
+    /// anymore. Only subtrees whose leaves were all appended can be pruned.
+    /// Returns `false` and leaves the tree untouched otherwise.
+    pub fn prune(&mut self, level: usize, index: u64) -> bool {
+        let end = (index as u128 + 1) << level;
+
+        if level == 0 || level > H || end > self.len() as u128 {
+            return false;
+        }
+
+        for below in 0..level {
+            let shift = level - below;
+            for i in index << shift..(index + 1) << shift {
+                self.store.remove(below, i);
+            }
+        }
+
+        true
+    }
+
+    /// Node at the given `level` and `index`, where level `0` holds the
+    /// leaves and level `H` the root.
+    fn node(&self, level: usize, index: u64) -> Result<BlsScalar, S::Error> {
+        let node = self.store.get(level, index)?;
+        Ok(node.unwrap_or(self.empty[level]))
+    }
+
+    /// Recompute the nodes from the leaf at `position` up to the root.
+    fn update_path(&mut self, position: u64) -> Result<(), S::Error> {
+        for level in 0..H {
+            let index = position >> (level + 1);
+
+            let left = self.node(level, index << 1)?;
+            let right = self.node(level, (index << 1) | 1)?;
+
+            self.store
+                .insert(level + 1, index, hash_node(&left, &right));
+        }
+
+        Ok(())
+    }
+}
+
+/// Hash the pairs of children of a level into their parents.
+fn hash_level(children: &[(BlsScalar, BlsScalar)]) -> Vec<BlsScalar> {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        children.par_iter().map(|(l, r)| hash_node(l, r)).collect()
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    {
+        children.iter().map(|(l, r)| hash_node(l, r)).collect()
+    }
+}
+
+/// Authentication path of a leaf in a [`MerkleTree`] of height `H`.
+///
+/// The branch holds the siblings from the leaf level up to the children of
+/// the root, and the bits of the position tell, for each level, whether the
+/// node being authenticated is the right child.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Opening<const H: usize> {
+    branch: [BlsScalar; H],
+    position: u64,
+}
+
+impl<const H: usize> Default for Opening<H> {
+    fn default() -> Self {
+        Self::new([BlsScalar::zero(); H], 0)
+    }
+}
+
+impl<const H: usize> Opening<H> {
+    /// Create an opening from its siblings and the position of the leaf.
+    pub const fn new(branch: [BlsScalar; H], position: u64) -> Self {
+        Self { branch, position }
+    }
+
+    /// Siblings of the path, from the leaf level up.
+    pub const fn branch(&self) -> &[BlsScalar; H] {
+        &self.branch
+    }
+
+    /// Position of the opened leaf.
+    pub const fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Compute the root of the tree containing `leaf` at this opening.
+    pub fn root(&self, leaf: &BlsScalar) -> HadesDigest {
+        self.branch
+            .iter()
+            .enumerate()
+            .fold(*leaf, |node, (level, sibling)| {
+                match (self.position >> level) & 1 {
+                    0 => hash_node(&node, sibling),
+                    _ => hash_node(sibling, &node),
+                }
+            })
+            .into()
+    }
+
+    /// Check that `leaf` belongs to the tree with the given `root`.
+    pub fn verify(&self, leaf: &BlsScalar, root: &HadesDigest) -> bool {
+        &self.root(leaf) == root
+    }
+}
+
+/// Hash two children into their parent node inside a plonk circuit.
+#[cfg(feature = "plonk")]
+pub fn hash_node_gadget(composer: &mut Composer, left: Witness, right: Witness) -> Witness {
+    let mut state = [Composer::ZERO; WIDTH];
+
+    state[0] = composer.append_constant(BlsScalar::from(NODE_CAPACITY));
+    state[1] = left;
+    state[2] = right;
+
+    GadgetStrategy::gadget(composer, &mut state);
+
+    state[1]
+}
+
+/// Compute, inside a plonk circuit, the root of the tree containing `leaf`
+/// at the given opening.
+///
+/// The siblings and the position of the opening are appended as private
+/// witnesses. The returned root is left unconstrained, so the caller can
+/// compare it with a public input.
+#[cfg(feature = "plonk")]
+pub fn opening_gadget<const H: usize>(
+    composer: &mut Composer,
+    opening: &Opening<H>,
+    leaf: Witness,
+) -> Witness {
+    let (branch, bits) = append_opening(composer, opening);
+    root_gadget(composer, leaf, &branch, &bits)
+}
+
+/// Prove, inside a plonk circuit, that replacing the leaf `old` with `new`
+/// at the given opening turns one root into another.
+///
+/// The siblings and the position of the opening are appended as private
+/// witnesses only once, and shared by both root computations. The returned
+/// old and new roots are left unconstrained, so the caller can compare them
+/// with public inputs.
+#[cfg(feature = "plonk")]
+pub fn update_gadget<const H: usize>(
+    composer: &mut Composer,
+    opening: &Opening<H>,
+    old: Witness,
+    new: Witness,
+) -> (Witness, Witness) {
+    let (branch, bits) = append_opening(composer, opening);
+
+    let old_root = root_gadget(composer, old, &branch, &bits);
+    let new_root = root_gadget(composer, new, &branch, &bits);
+
+    (old_root, new_root)
+}
+
+/// Append the siblings and the position bits of an opening as witnesses.
+///
+/// Every position bit is constrained to be boolean.
+#[cfg(feature = "plonk")]
+pub(crate) fn append_opening<const H: usize>(
+    composer: &mut Composer,
+    opening: &Opening<H>,
+) -> ([Witness; H], [Witness; H]) {
+    let mut branch = [Composer::ZERO; H];
+    let mut bits = [Composer::ZERO; H];
+
+    append_path(
+        composer,
+        &opening.branch,
+        opening.position,
+        &mut branch,
+        &mut bits,
+    );
+
+    (branch, bits)
+}
+
+/// Append the given siblings, and the bits of `position` selecting the side
+/// of each of them, as witnesses into `branch` and `bits`.
+///
+/// Every position bit is constrained to be boolean.
+#[cfg(feature = "plonk")]
+pub(crate) fn append_path(
+    composer: &mut Composer,
+    siblings: &[BlsScalar],
+    position: u64,
+    branch: &mut [Witness],
+    bits: &mut [Witness],
+) {
+    branch
+        .iter_mut()
+        .zip(bits.iter_mut())
+        .zip(siblings.iter())
+        .enumerate()
+        .for_each(|(level, ((sibling, bit), value))| {
+            *sibling = composer.append_witness(*value);
+
+            let side = (position >> level) & 1;
+            *bit = composer.append_witness(BlsScalar::from(side));
+            composer.component_boolean(*bit);
+        });
+}
+
+/// Hash `leaf` up to the root with the given siblings and position bits.
+#[cfg(feature = "plonk")]
+pub(crate) fn root_gadget(
+    composer: &mut Composer,
+    leaf: Witness,
+    branch: &[Witness],
+    bits: &[Witness],
+) -> Witness {
+    branch
+        .iter()
+        .zip(bits.iter())
+        .fold(leaf, |node, (sibling, bit)| {
+            let left = composer.component_select(*bit, *sibling, node);
+            let right = composer.component_select(*bit, node, *sibling);
+
+            hash_node_gadget(composer, left, right)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const H: usize = 4;
+
+    #[test]
+    fn empty_root() -> Result<(), Error> {
+        let tree = MerkleTree::<H>::new();
+        let empty = empty_subtrees(H);
+
+        assert!(tree.is_empty());
+        assert_eq!(tree.root()?, HadesDigest::from(empty[H]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn openings() -> Result<(), Error> {
+        let mut tree = MerkleTree::<H>::new();
+
+        for i in 0..5u64 {
+            let position = tree.push(BlsScalar::from(i + 1))?;
+            assert_eq!(position, i);
+        }
+
+        let root = tree.root()?;
+        for i in 0..5u64 {
+            let leaf = BlsScalar::from(i + 1);
+            let opening = tree.opening(i)?.unwrap();
+
+            assert!(opening.verify(&leaf, &root));
+            assert!(!opening.verify(&BlsScalar::from(42u64), &root));
+        }
+
+        assert!(tree.opening(5)?.is_none());
+
+        let previous = tree.update(2, BlsScalar::from(42u64))?;
+        assert_eq!(previous, Some(BlsScalar::from(3u64)));
+        assert_ne!(tree.root()?, root);
+
+        let opening = tree.opening(2)?.unwrap();
+        assert!(opening.verify(&BlsScalar::from(42u64), &tree.root()?));
+
+        Ok(())
+    }
+
+    #[test]
+    fn extend() {
+        let leaves: Vec<_> = (0..11u64).map(BlsScalar::from).collect();
+
+        let mut sequential = MerkleTree::<H>::new();
+        leaves.iter().for_each(|leaf| {
+            sequential.push(*leaf).unwrap();
+        });
+
+        let tree = MerkleTree::<H>::from_leaves(&leaves).unwrap();
+        assert_eq!(tree, sequential);
+
+        let mut tree = MerkleTree::<H>::from_leaves(&leaves[..3]).unwrap();
+        tree.extend(&leaves[3..]).unwrap();
+        assert_eq!(tree, sequential);
+
+        assert_eq!(tree.extend(&[BlsScalar::zero(); 6]), Err(Error::TreeFull));
+        assert_eq!(tree, sequential);
+    }
+
+    #[test]
+    fn full() {
+        let mut tree = MerkleTree::<2>::new();
+
+        for i in 0..4u64 {
+            tree.push(BlsScalar::from(i)).unwrap();
+        }
+
+        assert_eq!(tree.push(BlsScalar::one()), Err(Error::TreeFull));
+    }
+
+    #[cfg(feature = "plonk")]
+    mod gadget {
+        use super::*;
+        use dusk_plonk::prelude::Error;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        #[derive(Default)]
+        struct UpdateCircuit {
+            old: BlsScalar,
+            new: BlsScalar,
+            opening: Opening<H>,
+            old_root: BlsScalar,
+            new_root: BlsScalar,
+        }
+
+        impl Circuit for UpdateCircuit {
+            fn circuit(&self, composer: &mut Composer) -> Result<(), Error> {
+                let old = composer.append_witness(self.old);
+                let new = composer.append_witness(self.new);
+
+                let (old_root, new_root) = update_gadget(composer, &self.opening, old, new);
+
+                let public = composer.append_public(self.old_root);
+                composer.assert_equal(old_root, public);
+
+                let public = composer.append_public(self.new_root);
+                composer.assert_equal(new_root, public);
+
+                Ok(())
+            }
+        }
+
+        #[test]
+        fn update() -> Result<(), Error> {
+            let mut rng = StdRng::seed_from_u64(0xbeef);
+
+            let pp = PublicParameters::setup(1 << 14, &mut rng)?;
+            let (prover, verifier) = Compiler::compile::<UpdateCircuit>(&pp, b"merkle_update")?;
+
+            let mut tree = MerkleTree::<H>::new();
+            for i in 0..5u64 {
+                tree.push(BlsScalar::from(i + 1)).unwrap();
+            }
+
+            let old = BlsScalar::from(4u64);
+            let new = BlsScalar::from(42u64);
+            let opening = tree.opening(3).unwrap().unwrap();
+
+            let old_root = tree.root().unwrap().into();
+            tree.update(3, new).unwrap();
+            let new_root = tree.root().unwrap().into();
+
+            let circuit = UpdateCircuit {
+                old,
+                new,
+                opening,
+                old_root,
+                new_root,
+            };
+            let (proof, public_inputs) = prover.prove(&mut rng, &circuit)?;
+            verifier.verify(&proof, &public_inputs)?;
+
+            // The old leaf doesn't match the old root
+            let circuit = UpdateCircuit {
+                old: BlsScalar::from(5u64),
+                ..circuit
+            };
+            assert!(prover.prove(&mut rng, &circuit).is_err());
+
+            Ok(())
+        }
+    }
+}
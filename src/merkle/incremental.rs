@@ -0,0 +1,354 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Append-only Merkle tree that only stores its frontier.
+//!
+//! The frontier is the rightmost path of the tree: for every level it keeps
+//! the last left node, which is all that is needed to append a leaf and
+//! compute the new root with `H` node hashes.
+//!
+//! Marked leaves keep their authentication path up to date as new leaves
+//! are appended. The siblings to the left of a marked leaf are known when
+//! it is marked, while the ones to the right are filled in as the subtrees
+//! they root are completed.
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec::Vec;
+use dusk_bls12_381::BlsScalar;
+
+use super::{empty_subtrees, hash_node, Opening};
+use crate::{Error, HadesDigest};
+
+/// Rightmost path of an append-only tree of a given depth.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Frontier {
+    size: u64,
+    filled: Vec<BlsScalar>,
+    root: BlsScalar,
+}
+
+impl Frontier {
+    fn new(depth: usize, empty: &[BlsScalar]) -> Self {
+        Self {
+            size: 0,
+            filled: empty[..depth].to_vec(),
+            root: empty[depth],
+        }
+    }
+
+    fn depth(&self) -> usize {
+        self.filled.len()
+    }
+
+    fn is_full(&self) -> bool {
+        self.size as u128 >= 1u128 << self.depth()
+    }
+
+    fn append(&mut self, leaf: BlsScalar, empty: &[BlsScalar]) {
+        let mut index = self.size;
+        let mut node = leaf;
+
+        for (level, filled) in self.filled.iter_mut().enumerate() {
+            node = match index & 1 {
+                0 => {
+                    *filled = node;
+                    hash_node(&node, &empty[level])
+                }
+                _ => hash_node(filled, &node),
+            };
+            index >>= 1;
+        }
+
+        self.root = node;
+        self.size += 1;
+    }
+}
+
+/// Authentication path of a marked leaf that is kept up to date.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Tracked {
+    position: u64,
+    /// Frontier nodes at the time of marking, holding the left siblings.
+    left: Vec<BlsScalar>,
+    /// Completed right siblings, from the lowest level up.
+    right: Vec<BlsScalar>,
+    /// Right sibling currently being built.
+    cursor: Option<Frontier>,
+}
+
+impl Tracked {
+    fn new(position: u64, frontier: &Frontier) -> Self {
+        Self {
+            position,
+            left: frontier.filled.clone(),
+            right: Vec::new(),
+            cursor: None,
+        }
+    }
+
+    /// Levels at which the sibling of the marked leaf is on the right.
+    fn right_levels(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.left.len()).filter(|level| (self.position >> level) & 1 == 0)
+    }
+
+    fn append(&mut self, leaf: BlsScalar, empty: &[BlsScalar]) {
+        if self.cursor.is_none() {
+            let level = self.right_levels().nth(self.right.len());
+            match level {
+                Some(level) => self.cursor = Some(Frontier::new(level, empty)),
+                None => return,
+            }
+        }
+
+        if let Some(cursor) = &mut self.cursor {
+            cursor.append(leaf, empty);
+
+            if cursor.is_full() {
+                self.right.push(cursor.root);
+                self.cursor = None;
+            }
+        }
+    }
+
+    fn opening<const H: usize>(&self, empty: &[BlsScalar]) -> Opening<H> {
+        let mut branch = [BlsScalar::zero(); H];
+        let mut right = 0;
+
+        branch.iter_mut().enumerate().for_each(|(level, sibling)| {
+            *sibling = match (self.position >> level) & 1 {
+                1 => self.left[level],
+                _ => {
+                    let node = match self.right.get(right) {
+                        Some(node) => *node,
+                        None if right == self.right.len() => self
+                            .cursor
+                            .as_ref()
+                            .map(|cursor| cursor.root)
+                            .unwrap_or(empty[level]),
+                        None => empty[level],
+                    };
+                    right += 1;
+                    node
+                }
+            };
+        });
+
+        Opening::new(branch, self.position)
+    }
+}
+
+/// Everything that is restored when rewinding to a checkpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct State {
+    frontier: Frontier,
+    tracked: BTreeMap<u64, Tracked>,
+}
+
+/// Append-only Merkle tree of height `H` storing only its frontier.
+///
+/// The root is updated on every append, and the authentication paths of
+/// the marked leaves are kept in sync with it. Checkpoints snapshot the
+/// frontier and the marked paths, so the tree can be rewound after a chain
+/// reorganization.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncrementalTree<const H: usize> {
+    empty: Vec<BlsScalar>,
+    state: State,
+    checkpoints: VecDeque<State>,
+    max_checkpoints: usize,
+}
+
+impl<const H: usize> IncrementalTree<H> {
+    /// Create a new empty tree, keeping at most `max_checkpoints` checkpoints.
+    pub fn new(max_checkpoints: usize) -> Self {
+        let empty = empty_subtrees(H);
+        let frontier = Frontier::new(H, &empty);
+
+        Self {
+            empty,
+            state: State {
+                frontier,
+                tracked: BTreeMap::new(),
+            },
+            checkpoints: VecDeque::new(),
+            max_checkpoints,
+        }
+    }
+
+    /// Root of the tree.
+    pub fn root(&self) -> HadesDigest {
+        self.state.frontier.root.into()
+    }
+
+    /// Amount of leaves appended to the tree.
+    pub fn len(&self) -> u64 {
+        self.state.frontier.size
+    }
+
+    /// Return `true` if no leaf was appended to the tree.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Append a leaf to the tree, returning its position.
+    pub fn append(&mut self, leaf: BlsScalar) -> Result<u64, Error> {
+        let state = &mut self.state;
+
+        if state.frontier.is_full() {
+            return Err(Error::TreeFull);
+        }
+
+        let position = state.frontier.size;
+
+        state.frontier.append(leaf, &self.empty);
+        state
+            .tracked
+            .values_mut()
+            .for_each(|tracked| tracked.append(leaf, &self.empty));
+
+        Ok(position)
+    }
+
+    /// Mark the last appended leaf, so its authentication path is kept up
+    /// to date. Returns its position, or `None` if the tree is empty.
+    pub fn mark(&mut self) -> Option<u64> {
+        let state = &mut self.state;
+        let position = state.frontier.size.checked_sub(1)?;
+
+        state
+            .tracked
+            .entry(position)
+            .or_insert_with(|| Tracked::new(position, &state.frontier));
+
+        Some(position)
+    }
+
+    /// Stop tracking the leaf at `position`, returning `true` if it was
+    /// marked.
+    pub fn unmark(&mut self, position: u64) -> bool {
+        self.state.tracked.remove(&position).is_some()
+    }
+
+    /// Positions of the marked leaves.
+    pub fn marked(&self) -> impl Iterator<Item = u64> + '_ {
+        self.state.tracked.keys().copied()
+    }
+
+    /// Current authentication path of the marked leaf at `position`.
+    pub fn witness(&self, position: u64) -> Option<Opening<H>> {
+        self.state
+            .tracked
+            .get(&position)
+            .map(|tracked| tracked.opening(&self.empty))
+    }
+
+    /// Save the current state of the tree, dropping the oldest checkpoint if
+    /// there are more than `max_checkpoints`.
+    pub fn checkpoint(&mut self) {
+        if self.max_checkpoints == 0 {
+            return;
+        }
+
+        if self.checkpoints.len() == self.max_checkpoints {
+            self.checkpoints.pop_front();
+        }
+
+        self.checkpoints.push_back(self.state.clone());
+    }
+
+    /// Amount of checkpoints available to rewind to.
+    pub fn checkpoints(&self) -> usize {
+        self.checkpoints.len()
+    }
+
+    /// Restore the tree to the last checkpoint, removing it.
+    ///
+    /// Leaves appended and marks set after the checkpoint are discarded.
+    /// Returns `false` if there is no checkpoint to rewind to.
+    pub fn rewind(&mut self) -> bool {
+        match self.checkpoints.pop_back() {
+            Some(state) => {
+                self.state = state;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MerkleTree;
+
+    const H: usize = 4;
+
+    #[test]
+    fn witnesses() {
+        let mut tree = IncrementalTree::<H>::new(0);
+        let mut dense = MerkleTree::<H>::new();
+
+        assert_eq!(tree.root(), dense.root().unwrap());
+        assert!(tree.mark().is_none());
+
+        for i in 0..11u64 {
+            let leaf = BlsScalar::from(i + 1);
+
+            tree.append(leaf).unwrap();
+            dense.push(leaf).unwrap();
+
+            if i == 2 || i == 5 || i == 8 {
+                tree.mark();
+            }
+
+            assert_eq!(tree.root(), dense.root().unwrap());
+            for position in tree.marked() {
+                assert_eq!(tree.witness(position), dense.opening(position).unwrap());
+            }
+        }
+
+        assert!(tree.unmark(5));
+        assert!(tree.witness(5).is_none());
+    }
+
+    #[test]
+    fn rewind() {
+        let mut tree = IncrementalTree::<H>::new(2);
+
+        for i in 0..5u64 {
+            tree.append(BlsScalar::from(i)).unwrap();
+        }
+        tree.mark();
+
+        let root = tree.root();
+        let witness = tree.witness(4);
+
+        tree.checkpoint();
+        for i in 5..9u64 {
+            tree.append(BlsScalar::from(i)).unwrap();
+        }
+        tree.mark();
+        assert_ne!(tree.root(), root);
+
+        assert!(tree.rewind());
+        assert_eq!(tree.root(), root);
+        assert_eq!(tree.len(), 5);
+        assert_eq!(tree.witness(4), witness);
+        assert!(tree.witness(8).is_none());
+        assert!(!tree.rewind());
+    }
+
+    #[test]
+    fn full() {
+        let mut tree = IncrementalTree::<2>::new(0);
+
+        for i in 0..4u64 {
+            tree.append(BlsScalar::from(i)).unwrap();
+        }
+
+        assert_eq!(tree.append(BlsScalar::one()), Err(Error::TreeFull));
+    }
+}
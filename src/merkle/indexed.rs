@@ -0,0 +1,417 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Indexed Merkle tree, where the leaves form a linked list sorted by value.
+//!
+//! Every leaf stores its value together with the index and the value of
+//! the next leaf in the sorted order. Proving that `x` isn't in the tree
+//! only requires opening the "low leaf" of `x`: the leaf with the largest
+//! value smaller than `x`, and showing `low.value < x < low.next_value`.
+//! The last leaf of the list has `next_value = 0`.
+//!
+//! The tree is initialized with a zero leaf at position `0`, so every
+//! non-zero value has a low leaf.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use dusk_bls12_381::BlsScalar;
+
+use super::{MerkleTree, Opening};
+use crate::{Error, HadesDigest, ScalarStrategy, Strategy, WIDTH};
+
+#[cfg(feature = "plonk")]
+use crate::constraints;
+#[cfg(feature = "plonk")]
+use crate::GadgetStrategy;
+#[cfg(feature = "plonk")]
+use dusk_plonk::prelude::*;
+
+/// Amount of bits the values stored in an [`IndexedTree`] can have.
+///
+/// Bounding the values allows the circuit to compare them with a range
+/// check on their difference without wrapping around the field modulus.
+pub const VALUE_BITS: usize = 253;
+
+/// Capacity element used to hash the three elements of a leaf.
+const LEAF_CAPACITY: u64 = (1 << 3) - 1;
+
+/// Leaf of an [`IndexedTree`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct IndexedLeaf {
+    /// Value stored in the leaf.
+    pub value: BlsScalar,
+    /// Position of the leaf holding the next value.
+    pub next_index: u64,
+    /// Next value in the sorted order, or zero for the last leaf.
+    pub next_value: BlsScalar,
+}
+
+impl IndexedLeaf {
+    /// Hash the leaf into the node stored in the tree.
+    pub fn hash(&self) -> BlsScalar {
+        let mut state = [BlsScalar::zero(); WIDTH];
+
+        state[0] = BlsScalar::from(LEAF_CAPACITY);
+        state[1] = self.value;
+        state[2] = BlsScalar::from(self.next_index);
+        state[3] = self.next_value;
+
+        ScalarStrategy::new().perm(&mut state);
+
+        state[1]
+    }
+
+    /// Return `true` if `value` falls strictly between this leaf and the
+    /// next one.
+    pub fn is_low_leaf_of(&self, value: &BlsScalar) -> bool {
+        cmp(&self.value, value) == Ordering::Less
+            && (self.next_value == BlsScalar::zero()
+                || cmp(value, &self.next_value) == Ordering::Less)
+    }
+}
+
+/// Indexed Merkle tree of height `H`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedTree<const H: usize> {
+    tree: MerkleTree<H>,
+    leaves: Vec<IndexedLeaf>,
+    sorted: BTreeMap<[u8; 32], u64>,
+}
+
+impl<const H: usize> Default for IndexedTree<H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const H: usize> IndexedTree<H> {
+    /// Create a new tree holding only the zero leaf.
+    pub fn new() -> Self {
+        let zero = IndexedLeaf::default();
+
+        let mut tree = MerkleTree::new();
+        tree.push(zero.hash())
+            .expect("A tree always has room for the zero leaf");
+
+        let mut sorted = BTreeMap::new();
+        sorted.insert(key(&zero.value), 0);
+
+        Self {
+            tree,
+            leaves: alloc::vec![zero],
+            sorted,
+        }
+    }
+
+    /// Root of the tree.
+    pub fn root(&self) -> HadesDigest {
+        self.tree.root().expect("Reading from memory can't fail")
+    }
+
+    /// Amount of leaves in the tree, including the zero leaf.
+    pub fn len(&self) -> u64 {
+        self.tree.len()
+    }
+
+    /// Always `false`, since the tree holds at least the zero leaf.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Leaf at the given position.
+    pub fn leaf(&self, index: u64) -> Option<&IndexedLeaf> {
+        self.leaves.get(index as usize)
+    }
+
+    /// Return `true` if `value` is stored in the tree.
+    pub fn contains(&self, value: &BlsScalar) -> bool {
+        self.sorted.contains_key(&key(value))
+    }
+
+    /// Find the low leaf of `value` together with its position.
+    ///
+    /// Returns `None` if `value` is already stored in the tree.
+    pub fn low_leaf(&self, value: &BlsScalar) -> Option<(u64, IndexedLeaf)> {
+        if self.contains(value) {
+            return None;
+        }
+
+        self.sorted
+            .range(..key(value))
+            .next_back()
+            .map(|(_, &index)| (index, self.leaves[index as usize]))
+    }
+
+    /// Insert `value` in the tree, returning the position of its leaf.
+    ///
+    /// The low leaf of `value` is updated to point to the new leaf, and the
+    /// new leaf inherits the previous successor of the low leaf.
+    pub fn insert(&mut self, value: BlsScalar) -> Result<u64, Error> {
+        if !fits(&value) {
+            return Err(Error::ValueOutOfRange);
+        }
+
+        let (low_index, low) = self.low_leaf(&value).ok_or(Error::DuplicateValue)?;
+
+        let leaf = IndexedLeaf {
+            value,
+            next_index: low.next_index,
+            next_value: low.next_value,
+        };
+        let index = self.tree.push(leaf.hash())?;
+
+        let low = IndexedLeaf {
+            value: low.value,
+            next_index: index,
+            next_value: value,
+        };
+        self.tree.update(low_index, low.hash())?;
+
+        self.leaves[low_index as usize] = low;
+        self.leaves.push(leaf);
+        self.sorted.insert(key(&value), index);
+
+        Ok(index)
+    }
+
+    /// Prove that `value` isn't stored in the tree.
+    ///
+    /// Returns `None` if `value` is in the tree or doesn't fit in
+    /// [`VALUE_BITS`].
+    pub fn non_membership(&self, value: &BlsScalar) -> Option<NonMembership<H>> {
+        if !fits(value) {
+            return None;
+        }
+
+        let (index, low_leaf) = self.low_leaf(value)?;
+        let opening = self
+            .tree
+            .opening(index)
+            .expect("Reading from memory can't fail")?;
+
+        Some(NonMembership { low_leaf, opening })
+    }
+}
+
+/// Proof that a value isn't stored in an [`IndexedTree`] of height `H`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NonMembership<const H: usize> {
+    /// Low leaf of the value.
+    pub low_leaf: IndexedLeaf,
+    /// Opening of the low leaf.
+    pub opening: Opening<H>,
+}
+
+impl<const H: usize> NonMembership<H> {
+    /// Check that `value` isn't stored in the tree with the given `root`.
+    pub fn verify(&self, root: &HadesDigest, value: &BlsScalar) -> bool {
+        fits(value)
+            && self.low_leaf.is_low_leaf_of(value)
+            && self.opening.verify(&self.low_leaf.hash(), root)
+    }
+}
+
+/// Prove, inside a plonk circuit, that `value` isn't stored in a tree.
+///
+/// The low leaf and its opening are appended as private witnesses, and the
+/// circuit checks that `value` fits in [`VALUE_BITS`] and that
+/// `low.value < value < low.next_value` with range checks of [`VALUE_BITS`]
+/// bits. The upper bound is skipped if the low leaf is the last one of the
+/// list.
+///
+/// The returned root is left unconstrained, so the caller can compare it
+/// with a public input.
+#[cfg(feature = "plonk")]
+pub fn non_membership_gadget<const H: usize>(
+    composer: &mut Composer,
+    value: Witness,
+    proof: &NonMembership<H>,
+) -> Witness {
+    let low = &proof.low_leaf;
+    let one = BlsScalar::one();
+
+    let low_value = composer.append_witness(low.value);
+    let next_index = composer.append_witness(BlsScalar::from(low.next_index));
+    let next_value = composer.append_witness(low.next_value);
+
+    let mut state = [Composer::ZERO; WIDTH];
+    state[0] = composer.append_constant(BlsScalar::from(LEAF_CAPACITY));
+    state[1] = low_value;
+    state[2] = next_index;
+    state[3] = next_value;
+
+    GadgetStrategy::gadget(composer, &mut state);
+    let leaf = state[1];
+
+    constraints::range::<VALUE_BITS>(composer, value);
+
+    // low.value < value
+    let constraint = Constraint::new()
+        .left(1)
+        .a(value)
+        .right(-one)
+        .b(low_value)
+        .constant(-one);
+    let diff = composer.gate_add(constraint);
+    constraints::range::<VALUE_BITS>(composer, diff);
+
+    // value < low.next_value, unless the low leaf is the last one
+    let is_last = constraints::is_zero(composer, next_value);
+    let constraint = Constraint::new()
+        .left(1)
+        .a(next_value)
+        .right(-one)
+        .b(value)
+        .constant(-one);
+    let diff = composer.gate_add(constraint);
+    let diff = composer.component_select(is_last, Composer::ZERO, diff);
+    constraints::range::<VALUE_BITS>(composer, diff);
+
+    super::opening_gadget(composer, &proof.opening, leaf)
+}
+
+/// Return `true` if `value` fits in [`VALUE_BITS`].
+fn fits(value: &BlsScalar) -> bool {
+    value.to_bytes()[31] >> (VALUE_BITS - 248) == 0
+}
+
+/// Big-endian canonical encoding of a scalar, so that the byte order
+/// matches the integer order.
+fn key(value: &BlsScalar) -> [u8; 32] {
+    let mut bytes = value.to_bytes();
+    bytes.reverse();
+    bytes
+}
+
+/// Compare two scalars by their canonical integer representation.
+fn cmp(a: &BlsScalar, b: &BlsScalar) -> Ordering {
+    key(a).cmp(&key(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const H: usize = 4;
+
+    #[test]
+    fn insert() {
+        let mut tree = IndexedTree::<H>::new();
+
+        for v in [30u64, 10, 20] {
+            tree.insert(BlsScalar::from(v)).unwrap();
+        }
+
+        let (index, low) = tree.low_leaf(&BlsScalar::from(15u64)).unwrap();
+        assert_eq!(index, 2);
+        assert_eq!(low.value, BlsScalar::from(10u64));
+        assert_eq!(low.next_index, 3);
+        assert_eq!(low.next_value, BlsScalar::from(20u64));
+
+        let (index, low) = tree.low_leaf(&BlsScalar::from(42u64)).unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(low.next_value, BlsScalar::zero());
+
+        assert!(tree.low_leaf(&BlsScalar::from(20u64)).is_none());
+        assert_eq!(
+            tree.insert(BlsScalar::from(20u64)),
+            Err(Error::DuplicateValue)
+        );
+        assert_eq!(tree.insert(-BlsScalar::one()), Err(Error::ValueOutOfRange));
+    }
+
+    #[test]
+    fn non_membership() {
+        let mut tree = IndexedTree::<H>::new();
+
+        for v in [30u64, 10, 20] {
+            tree.insert(BlsScalar::from(v)).unwrap();
+        }
+
+        let root = tree.root();
+        for v in [5u64, 15, 25, 35] {
+            let value = BlsScalar::from(v);
+            let proof = tree.non_membership(&value).unwrap();
+
+            assert!(proof.verify(&root, &value));
+            assert!(!proof.verify(&root, &proof.low_leaf.value));
+        }
+
+        assert!(tree.non_membership(&BlsScalar::from(10u64)).is_none());
+    }
+
+    #[cfg(feature = "plonk")]
+    mod gadget {
+        use super::*;
+        use dusk_plonk::prelude::Error;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        #[derive(Default)]
+        struct NonMembershipCircuit {
+            value: BlsScalar,
+            root: BlsScalar,
+            proof: NonMembership<H>,
+        }
+
+        impl Circuit for NonMembershipCircuit {
+            fn circuit(&self, composer: &mut Composer) -> Result<(), Error> {
+                let value = composer.append_witness(self.value);
+                let root = non_membership_gadget(composer, value, &self.proof);
+
+                let public = composer.append_public(self.root);
+                composer.assert_equal(root, public);
+
+                Ok(())
+            }
+        }
+
+        #[test]
+        fn prove_non_membership() -> Result<(), Error> {
+            let mut rng = StdRng::seed_from_u64(0xbeef);
+
+            let pp = PublicParameters::setup(1 << 13, &mut rng)?;
+            let (prover, verifier) =
+                Compiler::compile::<NonMembershipCircuit>(&pp, b"indexed_non_membership")?;
+
+            let mut tree = IndexedTree::<H>::new();
+            for v in [30u64, 10, 20] {
+                tree.insert(BlsScalar::from(v)).unwrap();
+            }
+
+            let root = tree.root().into();
+            for v in [15u64, 35] {
+                let value = BlsScalar::from(v);
+                let proof = tree.non_membership(&value).unwrap();
+
+                let circuit = NonMembershipCircuit { value, root, proof };
+                let (proof, public_inputs) = prover.prove(&mut rng, &circuit)?;
+                verifier.verify(&proof, &public_inputs)?;
+            }
+
+            // Using the low leaf of 15 to prove that 20 isn't in the tree
+            let proof = tree.non_membership(&BlsScalar::from(15u64)).unwrap();
+            let circuit = NonMembershipCircuit {
+                value: BlsScalar::from(20u64),
+                root,
+                proof,
+            };
+            assert!(prover.prove(&mut rng, &circuit).is_err());
+
+            // Values that don't fit in VALUE_BITS are rejected, even above the
+            // last leaf
+            let value = BlsScalar::from_raw([0, 0, 0, 1 << 61]);
+            let proof = tree.non_membership(&BlsScalar::from(35u64)).unwrap();
+            assert!(!proof.verify(&tree.root(), &value));
+
+            let circuit = NonMembershipCircuit { value, root, proof };
+            assert!(prover.prove(&mut rng, &circuit).is_err());
+
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,527 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Merkle Mountain Range accumulator.
+//!
+//! An MMR of `n` leaves is a list of perfect binary trees, the mountains,
+//! one for every bit set in `n`, from the highest to the lowest. The roots
+//! of the mountains are the peaks, and the root of the MMR is obtained by
+//! bagging the peaks from right to left, starting from the amount of leaves.
+//!
+//! Mountains never change once they are complete, so every historical root
+//! can be proven consistent with the current one.
+
+use alloc::vec::Vec;
+use dusk_bls12_381::BlsScalar;
+
+use super::hash_node;
+use crate::{HadesDigest, ScalarStrategy, Strategy, WIDTH};
+
+#[cfg(feature = "plonk")]
+use crate::GadgetStrategy;
+#[cfg(feature = "plonk")]
+use dusk_plonk::prelude::*;
+
+/// Capacity element used to bag a peak with the accumulated right peaks.
+///
+/// Following the Poseidon paper, hashing a constant amount of `n` inputs
+/// into one output uses `n · 2^64` as capacity.
+const BAG_CAPACITY: BlsScalar = BlsScalar::from_raw([0, 2, 0, 0]);
+
+/// Bag a peak with the accumulation of the peaks to its right.
+fn hash_bag(peak: &BlsScalar, acc: &BlsScalar) -> BlsScalar {
+    let mut state = [BlsScalar::zero(); WIDTH];
+
+    state[0] = BAG_CAPACITY;
+    state[1] = *peak;
+    state[2] = *acc;
+
+    ScalarStrategy::new().perm(&mut state);
+
+    state[1]
+}
+
+/// Bag the peaks of an MMR of `size` leaves into its root.
+pub fn bag_peaks(peaks: &[BlsScalar], size: u64) -> BlsScalar {
+    peaks
+        .iter()
+        .rev()
+        .fold(BlsScalar::from(size), |acc, peak| hash_bag(peak, &acc))
+}
+
+/// Height and first leaf position of every mountain of an MMR of `size`
+/// leaves, from left to right.
+fn mountains(size: u64) -> impl Iterator<Item = (usize, u64)> {
+    let mut offset = 0;
+
+    (0..64).rev().filter_map(move |height| {
+        if (size >> height) & 1 == 0 {
+            return None;
+        }
+
+        let mountain = (height, offset);
+        offset += 1u64 << height;
+
+        Some(mountain)
+    })
+}
+
+/// Index and height of the mountain holding the leaf at `position` in an
+/// MMR of `size` leaves.
+fn mountain_of(position: u64, size: u64) -> Option<(usize, usize)> {
+    mountains(size)
+        .enumerate()
+        .find(|&(_, (height, offset))| position.wrapping_sub(offset) < 1u64 << height)
+        .map(|(index, (height, _))| (index, height))
+        .filter(|_| position < size)
+}
+
+/// Hash `node`, at `index` of its level, up with the given siblings.
+fn climb(node: BlsScalar, index: u64, siblings: &[BlsScalar]) -> BlsScalar {
+    siblings
+        .iter()
+        .enumerate()
+        .fold(node, |node, (level, sibling)| match (index >> level) & 1 {
+            0 => hash_node(&node, sibling),
+            _ => hash_node(sibling, &node),
+        })
+}
+
+/// Merkle Mountain Range accumulator.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Mmr {
+    /// Level `k` holds the roots of every complete subtree of height `k`.
+    levels: Vec<Vec<BlsScalar>>,
+}
+
+impl Mmr {
+    /// Create a new empty MMR.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Amount of leaves appended to the MMR.
+    pub fn len(&self) -> u64 {
+        self.levels.first().map(|l| l.len() as u64).unwrap_or(0)
+    }
+
+    /// Return `true` if no leaf was appended to the MMR.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Append a leaf to the MMR, returning its position.
+    pub fn push(&mut self, leaf: BlsScalar) -> u64 {
+        let position = self.len();
+
+        let mut level = 0;
+        let mut node = leaf;
+
+        loop {
+            if self.levels.len() == level {
+                self.levels.push(Vec::new());
+            }
+
+            let nodes = &mut self.levels[level];
+            nodes.push(node);
+
+            if nodes.len() % 2 == 1 {
+                break;
+            }
+
+            node = hash_node(&nodes[nodes.len() - 2], &nodes[nodes.len() - 1]);
+            level += 1;
+        }
+
+        position
+    }
+
+    /// Peaks of the MMR, from left to right.
+    pub fn peaks(&self) -> Vec<BlsScalar> {
+        self.peaks_at(self.len())
+    }
+
+    /// Root of the MMR.
+    pub fn root(&self) -> HadesDigest {
+        bag_peaks(&self.peaks(), self.len()).into()
+    }
+
+    /// Root the MMR had when it held `size` leaves.
+    ///
+    /// Returns `None` if `size` is larger than the current amount of leaves.
+    pub fn root_at(&self, size: u64) -> Option<HadesDigest> {
+        (size <= self.len()).then(|| bag_peaks(&self.peaks_at(size), size).into())
+    }
+
+    /// Proof of inclusion of the leaf at `position` in the current root.
+    pub fn proof(&self, position: u64) -> Option<MmrProof> {
+        let size = self.len();
+        let (_, height) = mountain_of(position, size)?;
+
+        let path: Vec<BlsScalar> = (0..height)
+            .map(|level| self.node(level, (position >> level) ^ 1))
+            .collect();
+
+        Some(MmrProof {
+            position,
+            size,
+            path,
+            peaks: self.peaks(),
+        })
+    }
+
+    /// Proof that the root the MMR had with `old_size` leaves is a prefix of
+    /// the current root.
+    pub fn consistency(&self, old_size: u64) -> Option<ConsistencyProof> {
+        let new_size = self.len();
+        if old_size > new_size {
+            return None;
+        }
+
+        let paths: Vec<Vec<BlsScalar>> = mountains(old_size)
+            .map(|(height, offset)| {
+                let (_, new_height) =
+                    mountain_of(offset, new_size).expect("The leaf is in the MMR");
+                let index = offset >> height;
+
+                (height..new_height)
+                    .map(|level| self.node(level, (index >> (level - height)) ^ 1))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        Some(ConsistencyProof {
+            old_size,
+            new_size,
+            old_peaks: self.peaks_at(old_size),
+            paths,
+            new_peaks: self.peaks(),
+        })
+    }
+
+    fn node(&self, level: usize, index: u64) -> BlsScalar {
+        self.levels[level][index as usize]
+    }
+
+    fn peaks_at(&self, size: u64) -> Vec<BlsScalar> {
+        mountains(size)
+            .map(|(height, offset)| self.node(height, offset >> height))
+            .collect()
+    }
+}
+
+/// Proof of inclusion of a leaf in an [`Mmr`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MmrProof {
+    /// Position of the leaf.
+    pub position: u64,
+    /// Amount of leaves of the MMR.
+    pub size: u64,
+    /// Siblings from the leaf up to the peak of its mountain.
+    pub path: Vec<BlsScalar>,
+    /// Every peak of the MMR, from left to right.
+    pub peaks: Vec<BlsScalar>,
+}
+
+impl MmrProof {
+    /// Check that `leaf` is included in the MMR with the given `root`.
+    pub fn verify(&self, leaf: &BlsScalar, root: &HadesDigest) -> bool {
+        let (index, height) = match mountain_of(self.position, self.size) {
+            Some(mountain) => mountain,
+            None => return false,
+        };
+
+        self.path.len() == height
+            && self.peaks.len() == mountains(self.size).count()
+            && climb(*leaf, self.position, &self.path) == self.peaks[index]
+            && HadesDigest::from(bag_peaks(&self.peaks, self.size)) == *root
+    }
+}
+
+/// Proof that an [`Mmr`] root is an extension of an older one.
+///
+/// Every old peak is a node of a new mountain, so it is hashed up to the
+/// matching new peak with its own path.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ConsistencyProof {
+    /// Amount of leaves of the old MMR.
+    pub old_size: u64,
+    /// Amount of leaves of the new MMR.
+    pub new_size: u64,
+    /// Peaks of the old MMR, from left to right.
+    pub old_peaks: Vec<BlsScalar>,
+    /// Siblings from every old peak up to the new peak above it.
+    pub paths: Vec<Vec<BlsScalar>>,
+    /// Peaks of the new MMR, from left to right.
+    pub new_peaks: Vec<BlsScalar>,
+}
+
+impl ConsistencyProof {
+    /// Check that the MMR with `new_root` extends the one with `old_root`.
+    pub fn verify(&self, old_root: &HadesDigest, new_root: &HadesDigest) -> bool {
+        if self.old_size > self.new_size
+            || self.old_peaks.len() != mountains(self.old_size).count()
+            || self.paths.len() != self.old_peaks.len()
+            || self.new_peaks.len() != mountains(self.new_size).count()
+        {
+            return false;
+        }
+
+        let extends = mountains(self.old_size)
+            .zip(self.old_peaks.iter().zip(self.paths.iter()))
+            .all(
+                |((height, offset), (peak, path))| match mountain_of(offset, self.new_size) {
+                    Some((index, new_height)) => {
+                        path.len() == new_height - height
+                            && climb(*peak, offset >> height, path) == self.new_peaks[index]
+                    }
+                    None => false,
+                },
+            );
+
+        extends
+            && HadesDigest::from(bag_peaks(&self.old_peaks, self.old_size)) == *old_root
+            && HadesDigest::from(bag_peaks(&self.new_peaks, self.new_size)) == *new_root
+    }
+}
+
+/// Bag, inside a plonk circuit, a peak with the accumulation of the peaks to
+/// its right.
+#[cfg(feature = "plonk")]
+fn hash_bag_gadget(composer: &mut Composer, peak: Witness, acc: Witness) -> Witness {
+    let mut state = [Composer::ZERO; WIDTH];
+
+    state[0] = composer.append_constant(BAG_CAPACITY);
+    state[1] = peak;
+    state[2] = acc;
+
+    GadgetStrategy::gadget(composer, &mut state);
+
+    state[1]
+}
+
+/// Compute, inside a plonk circuit, the root of the MMR of `size` leaves
+/// containing `leaf` with the given proof.
+///
+/// The shape of the circuit depends only on `H`: the same circuit proves
+/// inclusion in any MMR of less than `2^H` leaves, with the leaf in any of
+/// its mountains. The bits of `size` select which of the `H` possible peaks
+/// are bagged, and the leaf is hashed up to every possible height of its
+/// mountain, so the cost is `2·H - 1` permutations regardless of the size.
+///
+/// The caller appends `size`, either as a public input or as a private
+/// witness, and the gadget fails to prove if it doesn't fit in `H` bits.
+/// The height of the mountain, the path and the peaks are appended as
+/// private witnesses. The returned root is left unconstrained, so the caller
+/// can compare it with a public input.
+#[cfg(feature = "plonk")]
+pub fn mmr_gadget<const H: usize>(
+    composer: &mut Composer,
+    proof: &MmrProof,
+    leaf: Witness,
+    size: Witness,
+) -> Witness {
+    let size_bits = composer.component_decomposition::<H>(size);
+
+    // A mountain of an MMR of less than 2^H leaves is at most H - 1 high
+    let levels = H.saturating_sub(1);
+    let mut path = proof.path.clone();
+    path.resize(levels, BlsScalar::zero());
+
+    let mut branch = alloc::vec![Composer::ZERO; levels];
+    let mut bits = alloc::vec![Composer::ZERO; levels];
+    super::append_path(composer, &path, proof.position, &mut branch, &mut bits);
+
+    // Node above the leaf at every possible height of its mountain
+    let mut nodes = Vec::with_capacity(H);
+    nodes.push(leaf);
+    branch.iter().zip(bits.iter()).for_each(|(sibling, bit)| {
+        let node = nodes[nodes.len() - 1];
+        let node = super::root_gadget(composer, node, &[*sibling], &[*bit]);
+        nodes.push(node);
+    });
+
+    // Peaks indexed by the height of their mountain
+    let mut peaks = alloc::vec![BlsScalar::zero(); H];
+    mountains(proof.size)
+        .zip(proof.peaks.iter())
+        .filter(|((height, _), _)| *height < H)
+        .for_each(|((height, _), peak)| peaks[height] = *peak);
+
+    let mountain = mountain_of(proof.position, proof.size).map(|(_, height)| height);
+
+    // Exactly one height is selected for the mountain of the leaf, and that
+    // mountain must be part of the MMR
+    let mut selected = Composer::ZERO;
+    let mut present = Composer::ZERO;
+
+    let mut root = size;
+    for height in 0..H {
+        let is_mountain = BlsScalar::from((mountain == Some(height)) as u64);
+        let is_mountain = composer.append_witness(is_mountain);
+        composer.component_boolean(is_mountain);
+
+        let constraint = Constraint::new()
+            .left(1)
+            .a(selected)
+            .right(1)
+            .b(is_mountain);
+        selected = composer.gate_add(constraint);
+
+        let constraint = Constraint::new()
+            .mult(1)
+            .a(is_mountain)
+            .b(size_bits[height]);
+        let is_present = composer.gate_mul(constraint);
+
+        let constraint = Constraint::new().left(1).a(present).right(1).b(is_present);
+        present = composer.gate_add(constraint);
+
+        let peak = composer.append_witness(peaks[height]);
+        let peak = composer.component_select(is_mountain, nodes[height], peak);
+
+        let bagged = hash_bag_gadget(composer, peak, root);
+        root = composer.component_select(size_bits[height], bagged, root);
+    }
+
+    let one = composer.append_constant(BlsScalar::one());
+    composer.assert_equal(selected, one);
+    composer.assert_equal(present, one);
+
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mmr(size: u64) -> Mmr {
+        let mut mmr = Mmr::new();
+        (0..size).for_each(|i| {
+            mmr.push(BlsScalar::from(i + 1));
+        });
+        mmr
+    }
+
+    #[test]
+    fn inclusion() {
+        let mmr = mmr(11);
+        let root = mmr.root();
+
+        assert_eq!(mmr.peaks().len(), 3);
+
+        for position in 0..11 {
+            let leaf = BlsScalar::from(position + 1);
+            let proof = mmr.proof(position).unwrap();
+
+            assert!(proof.verify(&leaf, &root));
+            assert!(!proof.verify(&BlsScalar::zero(), &root));
+        }
+
+        assert!(mmr.proof(11).is_none());
+    }
+
+    #[test]
+    fn consistency() {
+        let mmr = mmr(13);
+        let new_root = mmr.root();
+
+        for old_size in 0..=13 {
+            let old_root = mmr.root_at(old_size).unwrap();
+            assert_eq!(old_root, self::mmr(old_size).root());
+
+            let proof = mmr.consistency(old_size).unwrap();
+            assert!(proof.verify(&old_root, &new_root));
+            assert!(!proof.verify(&new_root, &old_root) || old_size == 13);
+        }
+
+        assert!(mmr.consistency(14).is_none());
+    }
+
+    #[cfg(feature = "plonk")]
+    mod gadget {
+        use super::*;
+        use dusk_plonk::prelude::Error;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        const H: usize = 4;
+
+        #[derive(Default)]
+        struct MmrCircuit {
+            leaf: BlsScalar,
+            root: BlsScalar,
+            proof: MmrProof,
+        }
+
+        impl Circuit for MmrCircuit {
+            fn circuit(&self, composer: &mut Composer) -> Result<(), Error> {
+                let leaf = composer.append_witness(self.leaf);
+                let size = composer.append_public(BlsScalar::from(self.proof.size));
+                let root = mmr_gadget::<H>(composer, &self.proof, leaf, size);
+
+                let public = composer.append_public(self.root);
+                composer.assert_equal(root, public);
+
+                Ok(())
+            }
+        }
+
+        #[test]
+        fn prove_inclusion() -> Result<(), Error> {
+            let mut rng = StdRng::seed_from_u64(0xbeef);
+
+            let pp = PublicParameters::setup(1 << 14, &mut rng)?;
+            let (prover, verifier) = Compiler::compile::<MmrCircuit>(&pp, b"mmr_inclusion")?;
+
+            // The same keys prove inclusion in MMRs of any size below 2^H
+            for size in [1, 4, 11, 15] {
+                let mmr = mmr(size);
+                let root = mmr.root().into();
+
+                for position in 0..size {
+                    let leaf = BlsScalar::from(position + 1);
+                    let proof = mmr.proof(position).unwrap();
+
+                    let circuit = MmrCircuit { leaf, root, proof };
+                    let (proof, public_inputs) = prover.prove(&mut rng, &circuit)?;
+                    verifier.verify(&proof, &public_inputs)?;
+                }
+            }
+
+            let mmr = mmr(11);
+            let root = mmr.root().into();
+
+            let circuit = MmrCircuit {
+                leaf: BlsScalar::zero(),
+                root,
+                proof: mmr.proof(8).unwrap(),
+            };
+            assert!(prover.prove(&mut rng, &circuit).is_err());
+
+            // Claiming a different size doesn't yield the same root
+            let mut proof = mmr.proof(8).unwrap();
+            proof.size = 12;
+            let circuit = MmrCircuit {
+                leaf: BlsScalar::from(9),
+                root,
+                proof,
+            };
+            assert!(prover.prove(&mut rng, &circuit).is_err());
+
+            // MMRs too large for the circuit can't be proven
+            let mmr = self::mmr(16);
+            let circuit = MmrCircuit {
+                leaf: BlsScalar::one(),
+                root: mmr.root().into(),
+                proof: mmr.proof(0).unwrap(),
+            };
+            assert!(prover.prove(&mut rng, &circuit).is_err());
+
+            Ok(())
+        }
+    }
+}
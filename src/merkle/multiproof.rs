@@ -0,0 +1,253 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Openings of many leaves of a [`MerkleTree`] at once.
+//!
+//! The leaves are climbed level by level. Whenever two nodes being climbed
+//! are siblings, they are hashed together instead of being authenticated
+//! separately, so the proof only carries the siblings that can't be
+//! computed from the opened leaves, and the verifier only hashes every
+//! distinct node on the paths once.
+
+use alloc::vec::Vec;
+use dusk_bls12_381::BlsScalar;
+
+use super::{hash_node, MerkleTree, NodeStore};
+use crate::HadesDigest;
+
+/// Opening of many leaves of a [`MerkleTree`] of height `H`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MultiProof<const H: usize> {
+    positions: Vec<u64>,
+    nodes: Vec<BlsScalar>,
+}
+
+impl<const H: usize, S: NodeStore> MerkleTree<H, S> {
+    /// Open the leaves at the given positions with a single proof.
+    ///
+    /// The positions are sorted and deduplicated. Returns `None` if no
+    /// position is given or any of the leaves wasn't appended or was
+    /// pruned.
+    pub fn multiproof(&self, positions: &[u64]) -> Result<Option<MultiProof<H>>, S::Error> {
+        let mut positions = positions.to_vec();
+        positions.sort_unstable();
+        positions.dedup();
+
+        if positions.is_empty() {
+            return Ok(None);
+        }
+        for &position in &positions {
+            if self.leaf(position)?.is_none() {
+                return Ok(None);
+            }
+        }
+
+        let mut nodes = Vec::new();
+        let mut indexes = positions.clone();
+
+        for level in 0..H {
+            let mut parents = Vec::with_capacity(indexes.len());
+            let mut i = 0;
+
+            while i < indexes.len() {
+                let index = indexes[i];
+
+                if is_pair(&indexes, i) {
+                    i += 2;
+                } else {
+                    nodes.push(self.node(level, index ^ 1)?);
+                    i += 1;
+                }
+
+                parents.push(index >> 1);
+            }
+
+            indexes = parents;
+        }
+
+        Ok(Some(MultiProof { positions, nodes }))
+    }
+}
+
+impl<const H: usize> MultiProof<H> {
+    /// Create a proof from its sorted positions and the siblings that can't
+    /// be computed from the opened leaves, in climbing order.
+    pub fn new(positions: Vec<u64>, nodes: Vec<BlsScalar>) -> Self {
+        Self { positions, nodes }
+    }
+
+    /// Sorted positions of the opened leaves.
+    pub fn positions(&self) -> &[u64] {
+        &self.positions
+    }
+
+    /// Siblings carried by the proof, in climbing order.
+    pub fn nodes(&self) -> &[BlsScalar] {
+        &self.nodes
+    }
+
+    /// Compute the root of the tree containing the given leaves, ordered as
+    /// [`MultiProof::positions`].
+    ///
+    /// Returns `None` if the proof doesn't match the amount of leaves.
+    pub fn root(&self, leaves: &[BlsScalar]) -> Option<HadesDigest> {
+        self.climb(leaves, hash_node).map(HadesDigest::from)
+    }
+
+    /// Check that the given leaves belong to the tree with `root`.
+    pub fn verify(&self, leaves: &[BlsScalar], root: &HadesDigest) -> bool {
+        self.root(leaves).as_ref() == Some(root)
+    }
+
+    fn climb<F>(&self, leaves: &[BlsScalar], mut hash: F) -> Option<BlsScalar>
+    where
+        F: FnMut(&BlsScalar, &BlsScalar) -> BlsScalar,
+    {
+        let sorted = self.positions.windows(2).all(|w| w[0] < w[1]);
+        let in_range = self.positions.iter().all(|&p| (p as u128) < 1u128 << H);
+
+        if leaves.len() != self.positions.len() || !sorted || !in_range {
+            return None;
+        }
+
+        let mut siblings = self.nodes.iter();
+        let mut indexes = self.positions.clone();
+        let mut values = leaves.to_vec();
+
+        for _ in 0..H {
+            let mut parents = Vec::with_capacity(indexes.len());
+            let mut nodes = Vec::with_capacity(indexes.len());
+            let mut i = 0;
+
+            while i < indexes.len() {
+                let index = indexes[i];
+
+                let (left, right) = if is_pair(&indexes, i) {
+                    i += 2;
+                    (values[i - 2], values[i - 1])
+                } else {
+                    let sibling = *siblings.next()?;
+                    i += 1;
+
+                    match index & 1 {
+                        0 => (values[i - 1], sibling),
+                        _ => (sibling, values[i - 1]),
+                    }
+                };
+
+                parents.push(index >> 1);
+                nodes.push(hash(&left, &right));
+            }
+
+            indexes = parents;
+            values = nodes;
+        }
+
+        match siblings.next() {
+            Some(_) => None,
+            None => values.first().copied(),
+        }
+    }
+}
+
+/// Return `true` if the node at `i` is a left child whose sibling is the
+/// next node being climbed.
+fn is_pair(indexes: &[u64], i: usize) -> bool {
+    indexes[i] & 1 == 0 && indexes.get(i + 1) == Some(&(indexes[i] | 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    const H: usize = 8;
+
+    fn tree(size: u64) -> MerkleTree<H> {
+        let mut tree = MerkleTree::new();
+        (0..size).for_each(|i| {
+            tree.push(BlsScalar::from(i + 1)).unwrap();
+        });
+        tree
+    }
+
+    /// Verify the proof counting the permutations, and compare them with
+    /// verifying an opening per leaf.
+    fn permutations(proof: &MultiProof<H>, root: &HadesDigest) -> (usize, usize) {
+        let leaves: Vec<_> = proof
+            .positions()
+            .iter()
+            .map(|p| BlsScalar::from(p + 1))
+            .collect();
+
+        let mut count = 0;
+        let computed = proof.climb(&leaves, |l, r| {
+            count += 1;
+            hash_node(l, r)
+        });
+        assert_eq!(computed.map(HadesDigest::from).as_ref(), Some(root));
+
+        (count, proof.positions().len() * H)
+    }
+
+    #[test]
+    fn multiproof() -> Result<(), Error> {
+        let tree = tree(200);
+        let root = tree.root()?;
+
+        let proof = tree.multiproof(&[150, 4, 3, 101, 5, 6, 100, 4])?.unwrap();
+        assert_eq!(proof.positions(), &[3, 4, 5, 6, 100, 101, 150]);
+
+        let leaves: Vec<_> = proof
+            .positions()
+            .iter()
+            .map(|p| BlsScalar::from(p + 1))
+            .collect();
+        assert!(proof.verify(&leaves, &root));
+
+        let mut wrong = leaves.clone();
+        wrong[2] = BlsScalar::zero();
+        assert!(!proof.verify(&wrong, &root));
+        assert!(proof.root(&leaves[1..]).is_none());
+
+        assert!(tree.multiproof(&[])?.is_none());
+        assert!(tree.multiproof(&[3, 200])?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn permutation_count() -> Result<(), Error> {
+        let tree = tree(200);
+        let root = tree.root()?;
+
+        // A full subtree of 16 leaves is climbed with 15 hashes, and the
+        // remaining 4 levels with one hash each
+        let positions: Vec<u64> = (16..32).collect();
+        let proof = tree.multiproof(&positions)?.unwrap();
+        let (multi, naive) = permutations(&proof, &root);
+
+        assert_eq!(multi, 19);
+        assert_eq!(naive, 128);
+        assert_eq!(proof.nodes().len(), 4);
+
+        // Scattered leaves still share the top of their paths
+        let proof = tree.multiproof(&[3, 4, 5, 6, 100, 101, 150])?.unwrap();
+        let (multi, naive) = permutations(&proof, &root);
+
+        assert!(multi < naive);
+        assert!(proof.nodes().len() < naive);
+
+        // A single leaf costs exactly one opening
+        let proof = tree.multiproof(&[42])?.unwrap();
+        let (multi, naive) = permutations(&proof, &root);
+
+        assert_eq!(multi, naive);
+        assert_eq!(proof.nodes().len(), H);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Storage backends for the nodes of a [`MerkleTree`](super::MerkleTree).
+//!
+//! Nodes are addressed by their level, where level `0` holds the leaves,
+//! and their index inside the level. Writes are infallible and immediately
+//! visible, while reads can fail for the backends reading the nodes from a
+//! storage device, and [`NodeStore::commit`] makes the changes durable for
+//! the backends that persist them.
+
+use alloc::collections::BTreeMap;
+use dusk_bls12_381::BlsScalar;
+
+use crate::Error;
+
+/// Store persisted on the file system
+#[cfg(feature = "std")]
+mod file;
+
+#[cfg(feature = "std")]
+pub use file::FileStore;
+
+/// Storage for the nodes of a Merkle tree.
+pub trait NodeStore {
+    /// Error returned when reading the nodes or committing the changes.
+    ///
+    /// The tree returns its own errors, such as [`Error::TreeFull`],
+    /// converted into this type.
+    type Error: From<Error>;
+
+    /// Node at the given `level` and `index`, if it is stored.
+    fn get(&self, level: usize, index: u64) -> Result<Option<BlsScalar>, Self::Error>;
+
+    /// Store a node, replacing the previous one if any.
+    fn insert(&mut self, level: usize, index: u64, node: BlsScalar);
+
+    /// Remove a node, if it is stored.
+    fn remove(&mut self, level: usize, index: u64);
+
+    /// Amount of leaves appended to the tree.
+    fn len(&self) -> u64;
+
+    /// Set the amount of leaves appended to the tree.
+    fn set_len(&mut self, len: u64);
+
+    /// Return `true` if no leaf was appended to the tree.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Make the changes since the last commit durable.
+    fn commit(&mut self) -> Result<(), Self::Error>;
+}
+
+/// Store keeping the nodes in memory.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MemoryStore {
+    nodes: BTreeMap<(usize, u64), BlsScalar>,
+    len: u64,
+}
+
+impl MemoryStore {
+    /// Create a new empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Amount of nodes held by the store.
+    pub fn nodes(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+impl NodeStore for MemoryStore {
+    type Error = Error;
+
+    fn get(&self, level: usize, index: u64) -> Result<Option<BlsScalar>, Error> {
+        Ok(self.nodes.get(&(level, index)).copied())
+    }
+
+    fn insert(&mut self, level: usize, index: u64, node: BlsScalar) {
+        self.nodes.insert((level, index), node);
+    }
+
+    fn remove(&mut self, level: usize, index: u64) {
+        self.nodes.remove(&(level, index));
+    }
+
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    fn set_len(&mut self, len: u64) {
+        self.len = len;
+    }
+
+    fn commit(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MerkleTree;
+
+    const H: usize = 4;
+
+    #[test]
+    fn prune() -> Result<(), Error> {
+        let mut tree = MerkleTree::<H>::new();
+        for i in 0..10u64 {
+            tree.push(BlsScalar::from(i + 1))?;
+        }
+
+        let root = tree.root()?;
+        let opening = tree.opening(9)?.unwrap();
+        let nodes = tree.store().nodes();
+
+        // Only complete subtrees can be pruned
+        assert!(!tree.prune(2, 2));
+        assert!(!tree.prune(0, 0));
+
+        assert!(tree.prune(3, 0));
+        assert!(tree.store().nodes() < nodes);
+
+        assert_eq!(tree.root()?, root);
+        assert_eq!(tree.opening(9)?, Some(opening));
+        assert!(tree.opening(3)?.is_none());
+        assert!(tree.update(3, BlsScalar::zero())?.is_none());
+
+        tree.push(BlsScalar::from(11u64))?;
+        let opening = tree.opening(10)?.unwrap();
+        assert!(opening.verify(&BlsScalar::from(11u64), &tree.root()?));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,783 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Node store persisted on the file system.
+//!
+//! The store is a directory holding a file per level of the tree, where the
+//! node at `index` takes the fixed size slot at `index · SLOT_SIZE`, so any
+//! node is read with a single positioned read. Since the leaves are
+//! appended from the left, the files grow with the amount of leaves.
+//!
+//! Changes are first written to a log. The records written between two
+//! commits form a batch, terminated by a commit record holding the checksum
+//! of its records. Once the log is synced, the changes are written to the
+//! level files, and the log is emptied only after they are synced too.
+//!
+//! When the store is opened, the committed batches left in the log by a
+//! crash are applied again, while the first batch that is incomplete or
+//! doesn't match its checksum is discarded, so the store always reflects
+//! the last successful commit.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use dusk_bls12_381::BlsScalar;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use super::NodeStore;
+
+const INSERT: u8 = 1;
+const REMOVE: u8 = 2;
+const LEN: u8 = 3;
+const COMMIT: u8 = 0xff;
+
+const INSERT_SIZE: usize = 1 + 1 + 8 + 32;
+const REMOVE_SIZE: usize = 1 + 1 + 8;
+const LEN_SIZE: usize = 1 + 8;
+const COMMIT_SIZE: usize = 1 + 8;
+
+/// Size of a slot of a level file: a byte telling whether the node is
+/// stored, followed by the node.
+const SLOT_SIZE: usize = 1 + 32;
+
+/// Amount of nodes cached by [`FileStore::open`].
+const CACHE_NODES: usize = 4096;
+
+const LOG_FILE: &str = "log";
+const LEN_FILE: &str = "len";
+const LEVEL_PREFIX: &str = "level-";
+
+/// Change recorded in the log.
+enum Record {
+    Insert(usize, u64, BlsScalar),
+    Remove(usize, u64),
+    Len(u64),
+}
+
+/// Store persisting the nodes in a directory of the file system.
+///
+/// Nodes are read from the level files, going through a cache holding a
+/// bounded amount of them, so the memory used by the store doesn't grow
+/// with the tree. Only the changes that were not yet written to the level
+/// files are kept in memory until the next commit.
+///
+/// The space of pruned nodes can be reclaimed with [`FileStore::compact`].
+///
+/// Levels are logged in a single byte, so a node stored above level `255`
+/// makes every following commit fail with [`io::ErrorKind::InvalidInput`].
+#[derive(Debug)]
+pub struct FileStore {
+    path: PathBuf,
+    log: File,
+    len_file: File,
+    levels: Vec<Option<File>>,
+    len: u64,
+    changes: BTreeMap<(usize, u64), Option<BlsScalar>>,
+    pending: Vec<u8>,
+    unlogged: bool,
+    logged: u64,
+    cache: RefCell<Cache>,
+}
+
+impl FileStore {
+    /// Open the store in the directory at `path`, creating it if it doesn't
+    /// exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::open_with_cache(path, CACHE_NODES)
+    }
+
+    /// Open the store in the directory at `path`, caching at most `nodes`
+    /// of the nodes read from the level files.
+    pub fn open_with_cache<P: AsRef<Path>>(path: P, nodes: usize) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        fs::create_dir_all(&path)?;
+
+        let mut log = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path.join(LOG_FILE))?;
+
+        let mut len_file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path.join(LEN_FILE))?;
+
+        let mut len = [0u8; 8];
+        let len = match len_file.read_exact(&mut len) {
+            Ok(()) => u64::from_le_bytes(len),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => 0,
+            Err(e) => return Err(e),
+        };
+
+        let mut levels = Vec::new();
+        for entry in fs::read_dir(&path)? {
+            let name = entry?.file_name();
+            let level = name
+                .to_str()
+                .and_then(|name| name.strip_prefix(LEVEL_PREFIX))
+                .and_then(|level| level.parse::<usize>().ok());
+
+            if let Some(level) = level {
+                if levels.len() <= level {
+                    levels.resize_with(level + 1, || None);
+                }
+                levels[level] = Some(open_level(&path, level)?);
+            }
+        }
+        sync_dir(&path)?;
+
+        let mut bytes = Vec::new();
+        log.read_to_end(&mut bytes)?;
+
+        let mut store = Self {
+            path,
+            log,
+            len_file,
+            levels,
+            len,
+            changes: BTreeMap::new(),
+            pending: Vec::new(),
+            unlogged: false,
+            logged: 0,
+            cache: RefCell::new(Cache::new(nodes)),
+        };
+
+        let committed = store.replay(&bytes);
+        store.logged = committed as u64;
+
+        if committed > 0 {
+            store.checkpoint()?;
+        } else if !bytes.is_empty() {
+            store.log.set_len(0)?;
+            store.log.sync_data()?;
+        }
+
+        Ok(store)
+    }
+
+    /// Reclaim the space taken by the pruned nodes.
+    ///
+    /// Every level file is rewritten next to the current one with only the
+    /// stored nodes, leaving holes in place of the removed ones on the file
+    /// systems supporting sparse files, and atomically moved over it. A
+    /// crash leaves either the old or the new file in place. Uncommitted
+    /// changes are committed by the compaction.
+    pub fn compact(&mut self) -> io::Result<()> {
+        self.commit()?;
+
+        for level in 0..self.levels.len() {
+            let source = match &self.levels[level] {
+                Some(file) => file,
+                None => continue,
+            };
+
+            let path = level_path(&self.path, level);
+            let mut tmp = path.clone().into_os_string();
+            tmp.push(".compact");
+            let tmp = PathBuf::from(tmp);
+
+            let size = source.metadata()?.len();
+            let mut reader = BufReader::new(source);
+            reader.seek(SeekFrom::Start(0))?;
+
+            let mut target = File::create(&tmp)?;
+            let mut slot = [0u8; SLOT_SIZE];
+            let mut offset = 0;
+
+            while offset + SLOT_SIZE as u64 <= size {
+                reader.read_exact(&mut slot)?;
+                if slot[0] != 0 {
+                    target.seek(SeekFrom::Start(offset))?;
+                    target.write_all(&slot)?;
+                }
+                offset += SLOT_SIZE as u64;
+            }
+
+            target.set_len(size)?;
+            target.sync_all()?;
+
+            fs::rename(&tmp, &path)?;
+            sync_dir(&self.path)?;
+
+            self.levels[level] = Some(open_level(&self.path, level)?);
+        }
+
+        Ok(())
+    }
+
+    /// Record the changes of the committed batches of the log, returning
+    /// the length of the committed prefix.
+    fn replay(&mut self, bytes: &[u8]) -> usize {
+        let mut committed = 0;
+        let mut cursor = 0;
+        let mut batch = Vec::new();
+
+        while let Some(&tag) = bytes.get(cursor) {
+            let size = match tag {
+                INSERT => INSERT_SIZE,
+                REMOVE => REMOVE_SIZE,
+                LEN => LEN_SIZE,
+                COMMIT => COMMIT_SIZE,
+                _ => break,
+            };
+
+            let record = match bytes.get(cursor..cursor + size) {
+                Some(record) => record,
+                None => break,
+            };
+
+            if tag == COMMIT {
+                if read_u64(&record[1..]) != checksum(&bytes[committed..cursor]) {
+                    break;
+                }
+
+                batch.drain(..).for_each(|record| self.apply(record));
+                cursor += size;
+                committed = cursor;
+                continue;
+            }
+
+            match decode(record) {
+                Some(record) => batch.push(record),
+                None => break,
+            }
+            cursor += size;
+        }
+
+        committed
+    }
+
+    /// Log a change, to be written by the next commit, and apply it.
+    fn log(&mut self, record: Record) {
+        if encode(&mut self.pending, &record).is_err() {
+            self.unlogged = true;
+        }
+        self.apply(record);
+    }
+
+    fn apply(&mut self, record: Record) {
+        match record {
+            Record::Insert(level, index, node) => {
+                self.changes.insert((level, index), Some(node));
+                self.cache.get_mut().evict(level, index);
+            }
+            Record::Remove(level, index) => {
+                self.changes.insert((level, index), None);
+                self.cache.get_mut().evict(level, index);
+            }
+            Record::Len(len) => self.len = len,
+        }
+    }
+
+    /// Write the committed changes to the level files, and empty the log
+    /// once they are durable.
+    ///
+    /// If writing fails the changes are kept, and written again by the next
+    /// commit.
+    fn checkpoint(&mut self) -> io::Result<()> {
+        let changes = core::mem::take(&mut self.changes);
+
+        if let Err(e) = self.write_changes(&changes) {
+            self.changes = changes;
+            return Err(e);
+        }
+
+        self.log.set_len(0)?;
+        self.log.sync_data()?;
+        self.logged = 0;
+
+        Ok(())
+    }
+
+    fn write_changes(
+        &mut self,
+        changes: &BTreeMap<(usize, u64), Option<BlsScalar>>,
+    ) -> io::Result<()> {
+        let mut touched = Vec::new();
+        let mut created = false;
+
+        for (&(level, index), node) in changes {
+            let exists = matches!(self.levels.get(level), Some(Some(_)));
+
+            // Nodes of a level without a file are not stored
+            if node.is_none() && !exists {
+                continue;
+            }
+
+            if !exists {
+                if self.levels.len() <= level {
+                    self.levels.resize_with(level + 1, || None);
+                }
+                self.levels[level] = Some(open_level(&self.path, level)?);
+                created = true;
+            }
+
+            let mut slot = [0u8; SLOT_SIZE];
+            if let Some(node) = node {
+                slot[0] = 1;
+                slot[1..].copy_from_slice(&node.to_bytes());
+            }
+
+            let file = self.levels[level].as_mut().expect("The level file is open");
+            file.seek(SeekFrom::Start(slot_offset(index)?))?;
+            file.write_all(&slot)?;
+
+            if touched.last() != Some(&level) {
+                touched.push(level);
+            }
+        }
+
+        for level in touched {
+            if let Some(file) = &self.levels[level] {
+                file.sync_data()?;
+            }
+        }
+
+        self.len_file.seek(SeekFrom::Start(0))?;
+        self.len_file.write_all(&self.len.to_le_bytes())?;
+        self.len_file.sync_data()?;
+
+        if created {
+            sync_dir(&self.path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Read the node at `level` and `index` from its level file.
+    fn read(&self, level: usize, index: u64) -> io::Result<Option<BlsScalar>> {
+        let mut file = match self.levels.get(level) {
+            Some(Some(file)) => file,
+            _ => return Ok(None),
+        };
+
+        let mut slot = [0u8; SLOT_SIZE];
+        file.seek(SeekFrom::Start(slot_offset(index)?))?;
+
+        match file.read_exact(&mut slot) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+
+        match slot[0] {
+            0 => Ok(None),
+            1 => {
+                let mut node = [0u8; 32];
+                node.copy_from_slice(&slot[1..]);
+
+                Option::<BlsScalar>::from(BlsScalar::from_bytes(&node))
+                    .map(Some)
+                    .ok_or_else(|| invalid_data("the node is not canonical"))
+            }
+            _ => Err(invalid_data("the slot is corrupted")),
+        }
+    }
+}
+
+impl NodeStore for FileStore {
+    type Error = io::Error;
+
+    fn get(&self, level: usize, index: u64) -> io::Result<Option<BlsScalar>> {
+        if let Some(node) = self.changes.get(&(level, index)) {
+            return Ok(*node);
+        }
+
+        if let Some(node) = self.cache.borrow().get(level, index) {
+            return Ok(node);
+        }
+
+        let node = self.read(level, index)?;
+        self.cache.borrow_mut().insert(level, index, node);
+
+        Ok(node)
+    }
+
+    fn insert(&mut self, level: usize, index: u64, node: BlsScalar) {
+        self.log(Record::Insert(level, index, node));
+    }
+
+    fn remove(&mut self, level: usize, index: u64) {
+        self.log(Record::Remove(level, index));
+    }
+
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    fn set_len(&mut self, len: u64) {
+        self.log(Record::Len(len));
+    }
+
+    fn commit(&mut self) -> io::Result<()> {
+        if self.unlogged {
+            return Err(level_overflow());
+        }
+
+        if !self.pending.is_empty() {
+            let mut bytes = self.pending.clone();
+            seal(&mut bytes);
+
+            let written = self
+                .log
+                .write_all(&bytes)
+                .and_then(|_| self.log.sync_data());
+
+            if let Err(e) = written {
+                // Drop the partial batch, so the next commit appends to the
+                // committed prefix
+                self.log.set_len(self.logged)?;
+                return Err(e);
+            }
+
+            self.pending.clear();
+            self.logged += bytes.len() as u64;
+        }
+
+        if self.logged > 0 {
+            self.checkpoint()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Direct-mapped cache of the nodes read from the level files.
+///
+/// A node takes the place of the one mapped to the same entry, so the cache
+/// never holds more nodes than it has entries.
+#[derive(Debug)]
+struct Cache {
+    entries: Vec<Option<(usize, u64, Option<BlsScalar>)>>,
+}
+
+impl Cache {
+    fn new(nodes: usize) -> Self {
+        Self {
+            entries: alloc::vec![None; nodes],
+        }
+    }
+
+    fn entry(&self, level: usize, index: u64) -> Option<usize> {
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        let key = index ^ ((level as u64) << 57);
+        let hash = key.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 32;
+
+        Some(hash as usize % self.entries.len())
+    }
+
+    fn get(&self, level: usize, index: u64) -> Option<Option<BlsScalar>> {
+        match self.entries[self.entry(level, index)?] {
+            Some((l, i, node)) if l == level && i == index => Some(node),
+            _ => None,
+        }
+    }
+
+    fn insert(&mut self, level: usize, index: u64, node: Option<BlsScalar>) {
+        if let Some(entry) = self.entry(level, index) {
+            self.entries[entry] = Some((level, index, node));
+        }
+    }
+
+    fn evict(&mut self, level: usize, index: u64) {
+        if self.get(level, index).is_some() {
+            if let Some(entry) = self.entry(level, index) {
+                self.entries[entry] = None;
+            }
+        }
+    }
+}
+
+fn level_path(path: &Path, level: usize) -> PathBuf {
+    path.join(std::format!("{}{}", LEVEL_PREFIX, level))
+}
+
+fn open_level(path: &Path, level: usize) -> io::Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(level_path(path, level))
+}
+
+fn slot_offset(index: u64) -> io::Result<u64> {
+    index.checked_mul(SLOT_SIZE as u64).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the index is out of the range of the level file",
+        )
+    })
+}
+
+/// Sync the directory at `path`, making the creation and the renaming of
+/// its files durable.
+///
+/// Directories can't be opened as files on every platform, and only Unix
+/// requires them to be synced.
+fn sync_dir(path: &Path) -> io::Result<()> {
+    if cfg!(unix) {
+        File::open(path)?.sync_all()?;
+    }
+
+    Ok(())
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn level_overflow() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "the level doesn't fit in a log record",
+    )
+}
+
+/// Append the encoding of `record` to `bytes`, failing if its level doesn't
+/// fit in a byte.
+fn encode(bytes: &mut Vec<u8>, record: &Record) -> io::Result<()> {
+    match record {
+        Record::Insert(level, index, node) => {
+            let level = u8::try_from(*level).map_err(|_| level_overflow())?;
+
+            bytes.push(INSERT);
+            bytes.push(level);
+            bytes.extend_from_slice(&index.to_le_bytes());
+            bytes.extend_from_slice(&node.to_bytes());
+        }
+        Record::Remove(level, index) => {
+            let level = u8::try_from(*level).map_err(|_| level_overflow())?;
+
+            bytes.push(REMOVE);
+            bytes.push(level);
+            bytes.extend_from_slice(&index.to_le_bytes());
+        }
+        Record::Len(len) => {
+            bytes.push(LEN);
+            bytes.extend_from_slice(&len.to_le_bytes());
+        }
+    }
+
+    Ok(())
+}
+
+fn decode(record: &[u8]) -> Option<Record> {
+    match record[0] {
+        INSERT => {
+            let mut node = [0u8; 32];
+            node.copy_from_slice(&record[10..]);
+            let node = Option::<BlsScalar>::from(BlsScalar::from_bytes(&node))?;
+
+            Some(Record::Insert(
+                record[1] as usize,
+                read_u64(&record[2..]),
+                node,
+            ))
+        }
+        REMOVE => Some(Record::Remove(record[1] as usize, read_u64(&record[2..]))),
+        LEN => Some(Record::Len(read_u64(&record[1..]))),
+        _ => None,
+    }
+}
+
+/// Terminate the batch of records with its commit record.
+fn seal(bytes: &mut Vec<u8>) {
+    let checksum = checksum(bytes);
+
+    bytes.push(COMMIT);
+    bytes.extend_from_slice(&checksum.to_le_bytes());
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(&bytes[..8]);
+    u64::from_le_bytes(buf)
+}
+
+/// FNV-1a hash of the records of a batch, used to detect torn writes.
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MerkleTree;
+
+    const H: usize = 8;
+
+    type Tree = MerkleTree<H, FileStore>;
+
+    fn path(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(std::format!("dusk-hades-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        path
+    }
+
+    #[test]
+    fn reopen() -> io::Result<()> {
+        let path = path("reopen");
+
+        let mut tree = Tree::with_store(FileStore::open(&path)?);
+        for i in 0..10u64 {
+            tree.push(BlsScalar::from(i + 1))?;
+        }
+        tree.commit()?;
+
+        let root = tree.root()?;
+        let opening = tree.opening(7)?;
+
+        // Changes that are not committed are lost
+        tree.push(BlsScalar::from(42u64))?;
+        drop(tree);
+
+        // Every node is read from the level files through a single entry
+        let tree = Tree::with_store(FileStore::open_with_cache(&path, 1)?);
+        assert_eq!(tree.len(), 10);
+        assert_eq!(tree.root()?, root);
+        assert_eq!(tree.opening(7)?, opening);
+
+        fs::remove_dir_all(&path)
+    }
+
+    #[test]
+    fn torn_write() -> io::Result<()> {
+        let path = path("torn");
+
+        let mut tree = Tree::with_store(FileStore::open(&path)?);
+        for i in 0..10u64 {
+            tree.push(BlsScalar::from(i + 1))?;
+        }
+        tree.commit()?;
+        let root = tree.root()?;
+        drop(tree);
+
+        // Simulate a crash in the middle of writing a batch
+        let mut file = OpenOptions::new().append(true).open(path.join(LOG_FILE))?;
+        file.write_all(&[INSERT, 0, 10, 0, 0])?;
+        drop(file);
+
+        let mut tree = Tree::with_store(FileStore::open(&path)?);
+        assert_eq!(tree.root()?, root);
+
+        tree.push(BlsScalar::from(11u64))?;
+        tree.commit()?;
+        let root = tree.root()?;
+        drop(tree);
+
+        let tree = Tree::with_store(FileStore::open(&path)?);
+        assert_eq!(tree.len(), 11);
+        assert_eq!(tree.root()?, root);
+
+        fs::remove_dir_all(&path)
+    }
+
+    #[test]
+    fn replay() -> io::Result<()> {
+        let path = path("replay");
+        drop(FileStore::open(&path)?);
+
+        // Simulate a crash after a batch is committed to the log, but before
+        // it is written to the level files
+        let node = BlsScalar::from(42u64);
+        let mut bytes = Vec::new();
+        encode(&mut bytes, &Record::Insert(0, 3, node))?;
+        encode(&mut bytes, &Record::Len(4))?;
+        seal(&mut bytes);
+
+        let mut file = OpenOptions::new().append(true).open(path.join(LOG_FILE))?;
+        file.write_all(&bytes)?;
+        drop(file);
+
+        let store = FileStore::open(&path)?;
+        assert_eq!(store.len(), 4);
+        assert_eq!(store.get(0, 3)?, Some(node));
+        assert_eq!(fs::metadata(path.join(LOG_FILE))?.len(), 0);
+        drop(store);
+
+        let store = FileStore::open(&path)?;
+        assert_eq!(store.get(0, 3)?, Some(node));
+
+        fs::remove_dir_all(&path)
+    }
+
+    #[test]
+    fn corrupted() -> io::Result<()> {
+        let path = path("corrupted");
+
+        let mut tree = Tree::with_store(FileStore::open(&path)?);
+        tree.push(BlsScalar::one())?;
+        tree.commit()?;
+        drop(tree);
+
+        let mut file = OpenOptions::new().write(true).open(level_path(&path, 0))?;
+        file.write_all(&[0xff; SLOT_SIZE])?;
+        drop(file);
+
+        let tree = Tree::with_store(FileStore::open(&path)?);
+        assert!(tree.leaf(0).is_err());
+        assert!(tree.root().is_ok());
+
+        fs::remove_dir_all(&path)
+    }
+
+    #[test]
+    fn level_overflow() -> io::Result<()> {
+        let path = path("level_overflow");
+
+        let mut store = FileStore::open(&path)?;
+        store.insert(256, 0, BlsScalar::one());
+        assert_eq!(store.get(256, 0)?, Some(BlsScalar::one()));
+
+        let error = store.commit().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        drop(store);
+
+        // Nothing was written to the log
+        assert_eq!(fs::metadata(path.join(LOG_FILE))?.len(), 0);
+        assert_eq!(FileStore::open(&path)?.get(256, 0)?, None);
+
+        fs::remove_dir_all(&path)
+    }
+
+    #[test]
+    fn prune_and_compact() -> io::Result<()> {
+        let path = path("compact");
+
+        let mut tree = Tree::with_store(FileStore::open(&path)?);
+        for i in 0..64u64 {
+            tree.push(BlsScalar::from(i + 1))?;
+        }
+        tree.commit()?;
+
+        let root = tree.root()?;
+
+        assert!(tree.prune(5, 0));
+        let mut store = tree.into_store();
+        store.compact()?;
+        assert_eq!(fs::metadata(path.join(LOG_FILE))?.len(), 0);
+        drop(store);
+
+        let tree = Tree::with_store(FileStore::open(&path)?);
+        assert_eq!(tree.root()?, root);
+        assert!(tree.opening(3)?.is_none());
+        assert!(tree.opening(40)?.is_some());
+
+        fs::remove_dir_all(&path)
+    }
+}
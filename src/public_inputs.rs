@@ -0,0 +1,160 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Compression of many public inputs into a single one.
+//!
+//! The cost of verifying a plonk proof grows with its amount of public
+//! inputs. Instead of appending every public value, a circuit can witness
+//! them privately and expose only their hash, in the
+//! [`DOMAIN_PUBLIC_INPUTS`] domain of the sponge. The verifier hashes the
+//! full public data with [`compress_public_inputs`] and checks the proof
+//! against that single value.
+//!
+//! Existing `Circuit` implementations can't be wrapped unchanged: the
+//! `Composer` of dusk-plonk is a concrete type rather than a trait, so
+//! there is no way to intercept the calls of a circuit to
+//! `Composer::append_public` and witness the values instead. Circuits are
+//! ported to `CompressedCircuit` by appending their public values as
+//! witnesses and returning them, and are then proven as `Compressed`.
+
+use dusk_bls12_381::BlsScalar;
+
+use crate::sponge::{Sponge, DOMAIN_PUBLIC_INPUTS};
+use crate::HadesDigest;
+
+#[cfg(feature = "plonk")]
+use crate::SpongeGadget;
+#[cfg(feature = "plonk")]
+use alloc::vec::Vec;
+#[cfg(feature = "plonk")]
+use dusk_plonk::prelude::*;
+
+/// Compress the public data of a proof into its single public input.
+pub fn compress_public_inputs(inputs: &[BlsScalar]) -> HadesDigest {
+    let mut sponge = Sponge::with_domain(DOMAIN_PUBLIC_INPUTS);
+    sponge.absorb(inputs);
+    sponge.finalize().into()
+}
+
+/// Compress witnesses inside a plonk circuit, matching
+/// [`compress_public_inputs`].
+///
+/// The returned witness is left unconstrained, so the caller can append it
+/// as the public input of the circuit.
+#[cfg(feature = "plonk")]
+pub fn compress_public_inputs_gadget(composer: &mut Composer, inputs: &[Witness]) -> Witness {
+    let mut sponge = SpongeGadget::with_domain(composer, DOMAIN_PUBLIC_INPUTS);
+    sponge.absorb(composer, inputs);
+    sponge.finalize(composer)
+}
+
+/// Circuit whose public inputs are compressed by [`Compressed`].
+///
+/// It is written as a [`Circuit`], except that the values that would be
+/// public inputs are appended as witnesses and returned, in the order the
+/// verifier passes them to [`compress_public_inputs`]. A circuit calling
+/// [`Composer::append_public`] itself adds public inputs next to the
+/// compressed one.
+#[cfg(feature = "plonk")]
+pub trait CompressedCircuit: Default {
+    /// Append the gates of the circuit, returning the witnesses of its
+    /// public data.
+    fn circuit(&self, composer: &mut Composer) -> Result<Vec<Witness>, Error>;
+}
+
+/// Plonk circuit with a single public input, the compression of the public
+/// data of the inner [`CompressedCircuit`].
+///
+/// Only implementations of [`CompressedCircuit`] can be compressed, not
+/// arbitrary implementations of [`Circuit`], so an existing circuit has to
+/// be ported to return its public witnesses. The proof is verified with
+/// `[compress_public_inputs(data).into()]` as its public inputs.
+#[cfg(feature = "plonk")]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Compressed<C>(pub C);
+
+#[cfg(feature = "plonk")]
+impl<C: CompressedCircuit> Circuit for Compressed<C> {
+    fn circuit(&self, composer: &mut Composer) -> Result<(), Error> {
+        let inputs = self.0.circuit(composer)?;
+        let compressed = compress_public_inputs_gadget(composer, &inputs);
+
+        let public = composer.append_public(composer[compressed]);
+        composer.assert_equal(compressed, public);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compression() {
+        let inputs = [BlsScalar::one(), BlsScalar::zero()];
+
+        assert_ne!(compress_public_inputs(&inputs), crate::hash(&inputs));
+        assert_ne!(
+            compress_public_inputs(&inputs),
+            compress_public_inputs(&inputs[..1])
+        );
+    }
+
+    #[cfg(feature = "plonk")]
+    mod gadget {
+        use super::*;
+        use dusk_plonk::prelude::Error;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        /// Prove the knowledge of a preimage of each of many public hashes.
+        #[derive(Default)]
+        struct PreimagesCircuit {
+            preimages: [BlsScalar; 4],
+        }
+
+        impl CompressedCircuit for PreimagesCircuit {
+            fn circuit(&self, composer: &mut Composer) -> Result<Vec<Witness>, Error> {
+                Ok(self
+                    .preimages
+                    .iter()
+                    .map(|preimage| {
+                        let preimage = composer.append_witness(*preimage);
+                        crate::hash_gadget(composer, &[preimage])
+                    })
+                    .collect())
+            }
+        }
+
+        #[test]
+        fn compressed() -> Result<(), Error> {
+            let mut rng = StdRng::seed_from_u64(0xbeef);
+
+            let pp = PublicParameters::setup(1 << 13, &mut rng)?;
+            let (prover, verifier) =
+                Compiler::compile::<Compressed<PreimagesCircuit>>(&pp, b"compressed")?;
+
+            let preimages = [0u64, 1, 2, 3].map(BlsScalar::from);
+            let hashes: [BlsScalar; 4] = preimages.map(|x| crate::hash(&[x]).into());
+
+            let circuit = Compressed(PreimagesCircuit { preimages });
+            let (proof, public_inputs) = prover.prove(&mut rng, &circuit)?;
+
+            let expected: BlsScalar = compress_public_inputs(&hashes).into();
+            assert_eq!(public_inputs, alloc::vec![expected]);
+            verifier.verify(&proof, &[expected])?;
+
+            // The proof doesn't verify against other public data
+            let mut hashes = hashes;
+            hashes.swap(0, 1);
+            let other: BlsScalar = compress_public_inputs(&hashes).into();
+            assert!(verifier.verify(&proof, &[other]).is_err());
+
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,185 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Deterministic random number generator on the duplex sponge.
+//!
+//! The seed is absorbed in the [`DOMAIN_RNG`] domain of the sponge, and the
+//! random bytes are the low [`BYTES_PER_SCALAR`] bytes of the squeezed
+//! scalars. Since the scalars are uniform in a field much larger than
+//! `2^128`, their low bytes are uniform up to a negligible bias.
+
+use core::fmt;
+
+use dusk_bls12_381::BlsScalar;
+use rand_core::{impls, CryptoRng, Error, RngCore, SeedableRng};
+
+use crate::bytes::absorb_bytes;
+use crate::sponge::{Sponge, DOMAIN_RNG};
+
+/// Amount of random bytes taken from every squeezed scalar.
+pub const BYTES_PER_SCALAR: usize = 16;
+
+/// Cryptographically secure random number generator over the permutation.
+///
+/// New entropy can be mixed in with [`HadesRng::reseed`], and
+/// [`HadesRng::ratchet`] makes the bytes generated so far unrecoverable
+/// from the state of the generator.
+#[derive(Clone)]
+pub struct HadesRng {
+    sponge: Sponge,
+    buffer: [u8; BYTES_PER_SCALAR],
+    used: usize,
+}
+
+impl fmt::Debug for HadesRng {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HadesRng").finish_non_exhaustive()
+    }
+}
+
+impl HadesRng {
+    /// Create a generator seeded with the given scalars.
+    pub fn from_scalars(seed: &[BlsScalar]) -> Self {
+        let mut sponge = Sponge::with_domain(DOMAIN_RNG);
+        sponge.absorb(seed);
+
+        Self::with_sponge(sponge)
+    }
+
+    /// Create a generator seeded with the given bytes.
+    pub fn from_bytes(seed: &[u8]) -> Self {
+        let mut sponge = Sponge::with_domain(DOMAIN_RNG);
+        absorb_bytes(&mut sponge, seed);
+
+        Self::with_sponge(sponge)
+    }
+
+    fn with_sponge(sponge: Sponge) -> Self {
+        Self {
+            sponge,
+            buffer: [0u8; BYTES_PER_SCALAR],
+            used: BYTES_PER_SCALAR,
+        }
+    }
+
+    /// Mix the given scalars into the state of the generator.
+    ///
+    /// The bytes generated afterwards depend on both the previous state
+    /// and the new entropy.
+    pub fn reseed(&mut self, entropy: &[BlsScalar]) {
+        self.discard();
+        self.sponge.absorb(entropy);
+    }
+
+    /// Erase the information about the bytes generated so far from the
+    /// state of the generator, so they can't be recovered if the state is
+    /// later compromised.
+    pub fn ratchet(&mut self) {
+        self.discard();
+        self.sponge.ratchet();
+    }
+
+    /// Drop the buffered bytes.
+    fn discard(&mut self) {
+        self.buffer = [0u8; BYTES_PER_SCALAR];
+        self.used = BYTES_PER_SCALAR;
+    }
+}
+
+impl RngCore for HadesRng {
+    fn next_u32(&mut self) -> u32 {
+        impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, mut dest: &mut [u8]) {
+        while !dest.is_empty() {
+            if self.used == BYTES_PER_SCALAR {
+                let scalar = self.sponge.squeeze().to_bytes();
+                self.buffer.copy_from_slice(&scalar[..BYTES_PER_SCALAR]);
+                self.used = 0;
+            }
+
+            let n = (BYTES_PER_SCALAR - self.used).min(dest.len());
+            dest[..n].copy_from_slice(&self.buffer[self.used..self.used + n]);
+            self.buffer[self.used..self.used + n].fill(0);
+
+            self.used += n;
+            dest = &mut dest[n..];
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for HadesRng {}
+
+impl SeedableRng for HadesRng {
+    type Seed = [u8; 32];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        Self::from_bytes(&seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes<const N: usize>(rng: &mut HadesRng) -> [u8; N] {
+        let mut bytes = [0u8; N];
+        rng.fill_bytes(&mut bytes);
+        bytes
+    }
+
+    #[test]
+    fn deterministic() {
+        let seed = [BlsScalar::from(42u64)];
+
+        let mut rng = HadesRng::from_scalars(&seed);
+        let all: [u8; 40] = bytes(&mut rng);
+
+        // Filling in chunks gives the same stream
+        let mut rng = HadesRng::from_scalars(&seed);
+        let mut chunks = [0u8; 40];
+        chunks.chunks_mut(7).for_each(|chunk| rng.fill_bytes(chunk));
+        assert_eq!(all, chunks);
+
+        let mut rng = HadesRng::from_scalars(&[BlsScalar::from(43u64)]);
+        assert_ne!(all, bytes(&mut rng));
+
+        let mut rng = HadesRng::from_seed([42; 32]);
+        assert_eq!(rng.next_u64(), HadesRng::from_bytes(&[42; 32]).next_u64());
+    }
+
+    #[test]
+    fn reseed_and_ratchet() {
+        let mut rng = HadesRng::from_scalars(&[BlsScalar::from(42u64)]);
+        let _: [u8; 5] = bytes(&mut rng);
+
+        let mut reseeded = rng.clone();
+        reseeded.reseed(&[BlsScalar::one()]);
+
+        let mut ratcheted = rng.clone();
+        ratcheted.ratchet();
+
+        let mut again = rng.clone();
+        again.ratchet();
+
+        let next: [u8; 32] = bytes(&mut rng);
+        let after: [u8; 32] = bytes(&mut ratcheted);
+
+        assert_ne!(next, bytes(&mut reseeded));
+        assert_ne!(next, after);
+        assert_eq!(after, bytes(&mut again));
+    }
+}
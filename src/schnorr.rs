@@ -0,0 +1,262 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Schnorr signatures over JubJub with challenges hashed by the sponge.
+//!
+//! A signature of the message `m` under the public key `PK = G · sk` is a
+//! point `R = G · r`, for a random nonce `r`, and the scalar
+//! `u = r - c · sk`. The challenge `c` is the hash of `R`, `PK` and `m` in
+//! the [`DOMAIN_SCHNORR`] domain of the sponge, truncated to its low
+//! [`TRUNCATED_BITS`](crate::TRUNCATED_BITS) bits. A signature is valid if
+//! `G · u + PK · c = R`.
+
+use core::fmt;
+
+use dusk_bls12_381::BlsScalar;
+use dusk_jubjub::{JubJubAffine, JubJubExtended, JubJubScalar, GENERATOR_EXTENDED};
+use rand_core::{CryptoRng, RngCore};
+
+use crate::jubjub::truncate;
+use crate::sponge::{Sponge, DOMAIN_SCHNORR};
+
+#[cfg(feature = "plonk")]
+use crate::jubjub::truncate_gadget;
+#[cfg(feature = "plonk")]
+use crate::SpongeGadget;
+#[cfg(feature = "plonk")]
+use dusk_plonk::prelude::*;
+
+/// Secret key signing messages.
+#[derive(Clone, Copy)]
+pub struct SchnorrSecretKey(JubJubScalar);
+
+impl fmt::Debug for SchnorrSecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SchnorrSecretKey").finish_non_exhaustive()
+    }
+}
+
+impl SchnorrSecretKey {
+    /// Create a secret key from its scalar.
+    pub const fn new(secret: JubJubScalar) -> Self {
+        Self(secret)
+    }
+
+    /// Create a uniformly random secret key.
+    pub fn random<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        Self(random_scalar(rng))
+    }
+
+    /// Public key of the secret key.
+    pub fn public_key(&self) -> SchnorrPublicKey {
+        SchnorrPublicKey((GENERATOR_EXTENDED * self.0).into())
+    }
+
+    /// Sign a message with a random nonce.
+    pub fn sign<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        message: &[BlsScalar],
+    ) -> SchnorrSignature {
+        let nonce = random_scalar(rng);
+        let r: JubJubAffine = (GENERATOR_EXTENDED * nonce).into();
+
+        let c = challenge(&r, &self.public_key().0, message);
+        let u = nonce - c * self.0;
+
+        SchnorrSignature { u, r }
+    }
+}
+
+/// Public key verifying signatures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchnorrPublicKey(JubJubAffine);
+
+impl SchnorrPublicKey {
+    /// Create a public key from its point.
+    pub const fn new(point: JubJubAffine) -> Self {
+        Self(point)
+    }
+
+    /// Point of the public key.
+    pub const fn as_point(&self) -> &JubJubAffine {
+        &self.0
+    }
+
+    /// Check that `signature` signs `message` under this key.
+    pub fn verify(&self, message: &[BlsScalar], signature: &SchnorrSignature) -> bool {
+        let c = challenge(&signature.r, &self.0, message);
+
+        let point = GENERATOR_EXTENDED * signature.u + JubJubExtended::from(self.0) * c;
+        point == JubJubExtended::from(signature.r)
+    }
+}
+
+/// Signature of a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchnorrSignature {
+    u: JubJubScalar,
+    r: JubJubAffine,
+}
+
+impl Default for SchnorrSignature {
+    fn default() -> Self {
+        Self {
+            u: JubJubScalar::zero(),
+            r: JubJubAffine::identity(),
+        }
+    }
+}
+
+impl SchnorrSignature {
+    /// Create a signature from its scalar and point.
+    pub const fn new(u: JubJubScalar, r: JubJubAffine) -> Self {
+        Self { u, r }
+    }
+
+    /// Scalar `u` of the signature.
+    pub const fn u(&self) -> &JubJubScalar {
+        &self.u
+    }
+
+    /// Point `R` of the signature.
+    pub const fn r(&self) -> &JubJubAffine {
+        &self.r
+    }
+}
+
+/// Hash the nonce point, the public key and the message into a challenge.
+fn challenge(r: &JubJubAffine, public_key: &JubJubAffine, message: &[BlsScalar]) -> JubJubScalar {
+    let mut sponge = Sponge::with_domain(DOMAIN_SCHNORR);
+    sponge.absorb(&[r.get_u(), r.get_v(), public_key.get_u(), public_key.get_v()]);
+    sponge.absorb(message);
+
+    truncate(&sponge.finalize())
+}
+
+fn random_scalar<R: RngCore + CryptoRng>(rng: &mut R) -> JubJubScalar {
+    let mut bytes = [0u8; 64];
+    rng.fill_bytes(&mut bytes);
+
+    JubJubScalar::from_bytes_wide(&bytes)
+}
+
+/// Verify, inside a plonk circuit, that `signature` signs `message` under
+/// `public_key`, matching [`SchnorrPublicKey::verify`].
+///
+/// The signature is appended as private witnesses, and the challenge is
+/// computed with the sponge gadget and truncated in the circuit.
+#[cfg(feature = "plonk")]
+pub fn schnorr_gadget(
+    composer: &mut Composer,
+    public_key: WitnessPoint,
+    message: &[Witness],
+    signature: &SchnorrSignature,
+) -> Result<(), Error> {
+    let u = composer.append_witness(BlsScalar::from(signature.u));
+    let r = composer.append_point(signature.r);
+
+    let mut sponge = SpongeGadget::with_domain(composer, DOMAIN_SCHNORR);
+    sponge.absorb(
+        composer,
+        &[*r.x(), *r.y(), *public_key.x(), *public_key.y()],
+    );
+    sponge.absorb(composer, message);
+    let c = sponge.finalize(composer);
+    let c = truncate_gadget(composer, c);
+
+    let g_u = composer.component_mul_generator(u, GENERATOR_EXTENDED)?;
+    let pk_c = composer.component_mul_point(c, public_key);
+    let point = composer.component_add_point(g_u, pk_c);
+
+    composer.assert_equal_point(r, point);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    const MESSAGE: [BlsScalar; 2] = [BlsScalar::one(), BlsScalar::zero()];
+
+    #[test]
+    fn signature() {
+        let mut rng = StdRng::seed_from_u64(0xbeef);
+
+        let secret_key = SchnorrSecretKey::random(&mut rng);
+        let public_key = secret_key.public_key();
+        let signature = secret_key.sign(&mut rng, &MESSAGE);
+
+        assert!(public_key.verify(&MESSAGE, &signature));
+        assert!(!public_key.verify(&MESSAGE[..1], &signature));
+
+        let other = SchnorrSecretKey::random(&mut rng).public_key();
+        assert!(!other.verify(&MESSAGE, &signature));
+
+        let forged = SchnorrSignature::new(signature.u() + JubJubScalar::one(), *signature.r());
+        assert!(!public_key.verify(&MESSAGE, &forged));
+    }
+
+    #[cfg(feature = "plonk")]
+    mod gadget {
+        use super::*;
+        use dusk_plonk::prelude::Error;
+
+        struct SchnorrCircuit {
+            public_key: JubJubAffine,
+            message: [BlsScalar; 2],
+            signature: SchnorrSignature,
+        }
+
+        impl Default for SchnorrCircuit {
+            fn default() -> Self {
+                Self {
+                    public_key: JubJubAffine::identity(),
+                    message: [BlsScalar::zero(); 2],
+                    signature: SchnorrSignature::default(),
+                }
+            }
+        }
+
+        impl Circuit for SchnorrCircuit {
+            fn circuit(&self, composer: &mut Composer) -> Result<(), Error> {
+                let public_key = composer.append_public_point(self.public_key);
+                let message = self.message.map(|x| composer.append_public(x));
+
+                schnorr_gadget(composer, public_key, &message, &self.signature)
+            }
+        }
+
+        #[test]
+        fn verify() -> Result<(), Error> {
+            let mut rng = StdRng::seed_from_u64(0xbeef);
+
+            let pp = PublicParameters::setup(1 << 13, &mut rng)?;
+            let (prover, verifier) = Compiler::compile::<SchnorrCircuit>(&pp, b"schnorr")?;
+
+            let secret_key = SchnorrSecretKey::random(&mut rng);
+
+            let circuit = SchnorrCircuit {
+                public_key: *secret_key.public_key().as_point(),
+                message: MESSAGE,
+                signature: secret_key.sign(&mut rng, &MESSAGE),
+            };
+            let (proof, public_inputs) = prover.prove(&mut rng, &circuit)?;
+            verifier.verify(&proof, &public_inputs)?;
+
+            let circuit = SchnorrCircuit {
+                message: [BlsScalar::zero(); 2],
+                ..circuit
+            };
+            assert!(prover.prove(&mut rng, &circuit).is_err());
+
+            Ok(())
+        }
+    }
+}
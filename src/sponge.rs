@@ -0,0 +1,507 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Sponge construction over the `Hades252` permutation.
+//!
+//! The first element of the state is the capacity, and the remaining
+//! `WIDTH - 1` elements are the rate. Inputs are added to the rate, and the
+//! state is permuted every time the rate is full. The input is padded with
+//! a single `1` followed by zeroes, so inputs of different lengths never
+//! collide.
+//!
+//! Following the Poseidon paper, the capacity is initialized to `2^64` for
+//! variable-length hashing. Different uses of the sponge are separated by
+//! adding a domain tag to it, so the capacity is `2^64 + domain`.
+
+use dusk_bls12_381::BlsScalar;
+
+use crate::{HadesDigest, ScalarStrategy, Strategy, WIDTH};
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "plonk")]
+use crate::{constraints, GadgetStrategy};
+#[cfg(feature = "plonk")]
+use dusk_plonk::prelude::*;
+
+/// Amount of elements absorbed or squeezed per permutation.
+pub const RATE: usize = WIDTH - 1;
+
+/// Domain of [`hash`].
+pub const DOMAIN_HASH: u64 = 0;
+
+/// Domain of [`hash_bytes`](crate::hash_bytes).
+pub const DOMAIN_BYTES: u64 = 1;
+
+/// Domain of [`hash_points`](crate::hash_points).
+pub const DOMAIN_POINTS: u64 = 2;
+
+/// Domain of [`hash_to_curve`](crate::hash_to_curve).
+pub const DOMAIN_HASH_TO_CURVE: u64 = 3;
+
+/// Domain of [`HadesMac`](crate::HadesMac).
+pub const DOMAIN_MAC: u64 = 4;
+
+/// Domain of [`HadesKdf::extract`](crate::HadesKdf::extract).
+pub const DOMAIN_KDF_EXTRACT: u64 = 5;
+
+/// Domain of [`HadesKdf::expand`](crate::HadesKdf::expand).
+pub const DOMAIN_KDF_EXPAND: u64 = 6;
+
+/// Domain of [`HadesRng`](crate::HadesRng).
+pub const DOMAIN_RNG: u64 = 7;
+
+/// Domain of [`HadesTranscript`](crate::HadesTranscript).
+pub const DOMAIN_TRANSCRIPT: u64 = 8;
+
+/// Domain of [`Commitment`](crate::Commitment).
+pub const DOMAIN_COMMITMENT: u64 = 9;
+
+/// Domain of [`HadesAead`](crate::HadesAead).
+pub const DOMAIN_AEAD: u64 = 10;
+
+/// Domain of the hash chains of [`WotsSecretKey`](crate::WotsSecretKey).
+pub const DOMAIN_WOTS: u64 = 11;
+
+/// Domain of the compressed [`WotsPublicKey`](crate::WotsPublicKey).
+pub const DOMAIN_WOTS_KEY: u64 = 12;
+
+/// Domain of the challenges of [`SchnorrSignature`](crate::SchnorrSignature).
+pub const DOMAIN_SCHNORR: u64 = 13;
+
+/// Domain of [`compress_public_inputs`](crate::compress_public_inputs).
+pub const DOMAIN_PUBLIC_INPUTS: u64 = 14;
+
+/// Sponge absorbing and squeezing `BlsScalar` elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sponge {
+    state: [BlsScalar; WIDTH],
+    position: usize,
+    squeezing: bool,
+}
+
+impl Default for Sponge {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sponge {
+    /// Create a new sponge for [`DOMAIN_HASH`].
+    pub fn new() -> Self {
+        Self::with_domain(DOMAIN_HASH)
+    }
+
+    /// Create a new sponge for the given domain.
+    pub fn with_domain(domain: u64) -> Self {
+        let mut state = [BlsScalar::zero(); WIDTH];
+        state[0] = capacity(domain);
+
+        Self {
+            state,
+            position: 0,
+            squeezing: false,
+        }
+    }
+
+    /// Absorb the given elements.
+    ///
+    /// Absorbing after squeezing starts a new absorption on the permuted
+    /// state.
+    pub fn absorb(&mut self, input: &[BlsScalar]) {
+        if self.squeezing {
+            self.squeezing = false;
+            self.position = RATE;
+        }
+
+        input.iter().for_each(|x| {
+            if self.position == RATE {
+                self.permute();
+                self.position = 0;
+            }
+
+            self.state[1 + self.position] += x;
+            self.position += 1;
+        });
+    }
+
+    /// Squeeze an element out of the sponge.
+    ///
+    /// The first squeeze after absorbing pads the input and permutes the
+    /// state.
+    pub fn squeeze(&mut self) -> BlsScalar {
+        if !self.squeezing {
+            self.absorb(&[BlsScalar::one()]);
+            self.permute();
+
+            self.squeezing = true;
+            self.position = 0;
+        }
+
+        if self.position == RATE {
+            self.permute();
+            self.position = 0;
+        }
+
+        self.position += 1;
+        self.state[self.position]
+    }
+
+    /// Squeeze a single element, consuming the sponge.
+    pub fn finalize(mut self) -> BlsScalar {
+        self.squeeze()
+    }
+
+    /// Permute the state and erase its rate.
+    ///
+    /// The permutation can be inverted, so the state of the sponge reveals
+    /// everything it absorbed and squeezed. Erasing the rate makes the
+    /// previous states unrecoverable, while the capacity keeps the sponge
+    /// unpredictable.
+    pub fn ratchet(&mut self) {
+        self.permute();
+        self.state[1..]
+            .iter_mut()
+            .for_each(|x| *x = BlsScalar::zero());
+
+        self.position = 0;
+        self.squeezing = false;
+    }
+
+    fn permute(&mut self) {
+        ScalarStrategy::new().perm(&mut self.state);
+    }
+}
+
+/// Sponge absorbing and squeezing witnesses, matching [`Sponge`].
+#[cfg(feature = "plonk")]
+#[derive(Debug, Clone, Copy)]
+pub struct SpongeGadget {
+    state: [Witness; WIDTH],
+    position: usize,
+    squeezing: bool,
+}
+
+#[cfg(feature = "plonk")]
+impl SpongeGadget {
+    /// Create a new sponge for [`DOMAIN_HASH`].
+    pub fn new(composer: &mut Composer) -> Self {
+        Self::with_domain(composer, DOMAIN_HASH)
+    }
+
+    /// Create a new sponge for the given domain.
+    pub fn with_domain(composer: &mut Composer, domain: u64) -> Self {
+        let mut state = [Composer::ZERO; WIDTH];
+        state[0] = composer.append_constant(capacity(domain));
+
+        Self {
+            state,
+            position: 0,
+            squeezing: false,
+        }
+    }
+
+    /// Absorb the given witnesses.
+    pub fn absorb(&mut self, composer: &mut Composer, input: &[Witness]) {
+        if self.squeezing {
+            self.squeezing = false;
+            self.position = RATE;
+        }
+
+        input.iter().for_each(|x| {
+            if self.position == RATE {
+                GadgetStrategy::gadget(composer, &mut self.state);
+                self.position = 0;
+            }
+
+            let word = &mut self.state[1 + self.position];
+            let constraint = Constraint::new().left(1).a(*word).right(1).b(*x);
+            *word = composer.gate_add(constraint);
+
+            self.position += 1;
+        });
+    }
+
+    /// Squeeze a witness out of the sponge.
+    pub fn squeeze(&mut self, composer: &mut Composer) -> Witness {
+        if !self.squeezing {
+            let one = composer.append_constant(BlsScalar::one());
+            self.absorb(composer, &[one]);
+            GadgetStrategy::gadget(composer, &mut self.state);
+
+            self.squeezing = true;
+            self.position = 0;
+        }
+
+        if self.position == RATE {
+            GadgetStrategy::gadget(composer, &mut self.state);
+            self.position = 0;
+        }
+
+        self.position += 1;
+        self.state[self.position]
+    }
+
+    /// Squeeze a single witness, consuming the sponge.
+    pub fn finalize(mut self, composer: &mut Composer) -> Witness {
+        self.squeeze(composer)
+    }
+}
+
+/// Capacity element of the sponge for the given domain.
+pub(crate) const fn capacity(domain: u64) -> BlsScalar {
+    BlsScalar::from_raw([domain, 1, 0, 0])
+}
+
+/// Hash an arbitrary amount of elements into a single one.
+pub fn hash(input: &[BlsScalar]) -> HadesDigest {
+    let mut sponge = Sponge::new();
+    sponge.absorb(input);
+    sponge.finalize().into()
+}
+
+/// Hash an arbitrary amount of witnesses into a single one, matching
+/// [`hash`].
+#[cfg(feature = "plonk")]
+pub fn hash_gadget(composer: &mut Composer, input: &[Witness]) -> Witness {
+    let mut sponge = SpongeGadget::new(composer);
+    sponge.absorb(composer, input);
+    sponge.finalize(composer)
+}
+
+/// Hash the first `len` witnesses of `input` inside a plonk circuit,
+/// matching [`hash`] on them, where `len` is a private witness.
+///
+/// The length of `input` is the maximum length supported by the circuit,
+/// and `len` is constrained to be at most that. Every block the longest
+/// input would absorb is permuted, the elements from `len` on are replaced
+/// by the padding, and the output is selected from the state after the
+/// block holding the padding.
+#[cfg(feature = "plonk")]
+pub fn hash_variable_gadget(composer: &mut Composer, input: &[Witness], len: Witness) -> Witness {
+    let blocks = input.len() / RATE + 1;
+
+    // is_end[i] = 1 if i == len, which happens exactly once for len <= max
+    let mut is_end = Vec::with_capacity(blocks * RATE);
+    (0..blocks * RATE).for_each(|i| {
+        let end = if i <= input.len() {
+            let constraint = Constraint::new()
+                .left(1)
+                .a(len)
+                .constant(-BlsScalar::from(i as u64));
+            let distance = composer.gate_add(constraint);
+            constraints::is_zero(composer, distance)
+        } else {
+            Composer::ZERO
+        };
+        is_end.push(end);
+    });
+
+    let ends = is_end.iter().fold(Composer::ZERO, |sum, end| {
+        let constraint = Constraint::new().left(1).a(sum).right(1).b(*end);
+        composer.gate_add(constraint)
+    });
+    let one = composer.append_constant(BlsScalar::one());
+    composer.assert_equal(ends, one);
+
+    let mut state = [Composer::ZERO; WIDTH];
+    state[0] = composer.append_constant(capacity(DOMAIN_HASH));
+
+    let mut ended = Composer::ZERO;
+    let mut output = Composer::ZERO;
+
+    is_end.chunks(RATE).enumerate().for_each(|(block, is_end)| {
+        let mut is_last = Composer::ZERO;
+
+        is_end.iter().enumerate().for_each(|(j, end)| {
+            let i = block * RATE + j;
+
+            // The element is absorbed if i < len, and replaced by the
+            // padding if i == len
+            let mut element = *end;
+            if let Some(x) = input.get(i) {
+                let constraint = Constraint::new()
+                    .left(1)
+                    .a(ended)
+                    .right(1)
+                    .b(*end)
+                    .constant(-BlsScalar::one());
+                let minus_before = composer.gate_add(constraint);
+
+                let constraint = Constraint::new()
+                    .mult(-BlsScalar::one())
+                    .a(minus_before)
+                    .b(*x);
+                let kept = composer.gate_mul(constraint);
+
+                let constraint = Constraint::new().left(1).a(kept).right(1).b(*end);
+                element = composer.gate_add(constraint);
+            }
+
+            let constraint = Constraint::new().left(1).a(ended).right(1).b(*end);
+            ended = composer.gate_add(constraint);
+
+            let constraint = Constraint::new().left(1).a(is_last).right(1).b(*end);
+            is_last = composer.gate_add(constraint);
+
+            let word = &mut state[1 + j];
+            let constraint = Constraint::new().left(1).a(*word).right(1).b(element);
+            *word = composer.gate_add(constraint);
+        });
+
+        GadgetStrategy::gadget(composer, &mut state);
+
+        let constraint = Constraint::new().mult(1).a(is_last).b(state[1]);
+        let selected = composer.gate_mul(constraint);
+
+        let constraint = Constraint::new().left(1).a(output).right(1).b(selected);
+        output = composer.gate_add(constraint);
+    });
+
+    output
+}
+
+/// Hash every input independently, as [`hash`] does.
+///
+/// With the `parallel` feature the inputs are hashed on all the cores of
+/// the machine.
+#[cfg(feature = "alloc")]
+pub fn batch_hash<I>(inputs: &[I]) -> Vec<HadesDigest>
+where
+    I: AsRef<[BlsScalar]> + Sync,
+{
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        inputs
+            .par_iter()
+            .map(|input| hash(input.as_ref()))
+            .collect()
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    {
+        inputs.iter().map(|input| hash(input.as_ref())).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn padding() {
+        let x = BlsScalar::from(42u64);
+
+        assert_ne!(hash(&[]), hash(&[BlsScalar::zero()]));
+        assert_ne!(hash(&[x]), hash(&[x, BlsScalar::zero()]));
+        assert_ne!(hash(&[x; RATE]), hash(&[x; RATE + 1]));
+
+        let mut sponge = Sponge::with_domain(1);
+        sponge.absorb(&[x; 3]);
+        assert_ne!(hash(&[x; 3]), HadesDigest::from(sponge.finalize()));
+    }
+
+    #[test]
+    fn streaming() {
+        let input: [BlsScalar; 11] = core::array::from_fn(|i| BlsScalar::from(i as u64));
+
+        let mut sponge = Sponge::new();
+        sponge.absorb(&input[..3]);
+        sponge.absorb(&input[3..]);
+
+        assert_eq!(HadesDigest::from(sponge.finalize()), hash(&input));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn batch() {
+        let inputs: Vec<Vec<BlsScalar>> = (0..20u64)
+            .map(|i| (0..i).map(BlsScalar::from).collect())
+            .collect();
+
+        let sequential: Vec<_> = inputs.iter().map(|input| hash(input)).collect();
+
+        assert_eq!(batch_hash(&inputs), sequential);
+    }
+
+    #[cfg(feature = "plonk")]
+    mod gadget {
+        use super::*;
+        use dusk_plonk::prelude::Error;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        const MAX: usize = 6;
+
+        #[derive(Default)]
+        struct VariableCircuit {
+            input: [BlsScalar; MAX],
+            len: usize,
+            hash: BlsScalar,
+        }
+
+        impl Circuit for VariableCircuit {
+            fn circuit(&self, composer: &mut Composer) -> Result<(), Error> {
+                let input = self.input.map(|x| composer.append_witness(x));
+                let len = composer.append_witness(BlsScalar::from(self.len as u64));
+
+                let hash = hash_variable_gadget(composer, &input, len);
+
+                let public = composer.append_public(self.hash);
+                composer.assert_equal(hash, public);
+
+                Ok(())
+            }
+        }
+
+        #[test]
+        fn hash_variable() -> Result<(), Error> {
+            let mut rng = StdRng::seed_from_u64(0xbeef);
+
+            let pp = PublicParameters::setup(1 << 13, &mut rng)?;
+            let (prover, verifier) = Compiler::compile::<VariableCircuit>(&pp, b"hash_variable")?;
+
+            let input: [BlsScalar; MAX] = core::array::from_fn(|i| BlsScalar::from(i as u64 + 1));
+
+            for len in [0, 1, 3, 4, 5, MAX] {
+                let circuit = VariableCircuit {
+                    input,
+                    len,
+                    hash: hash(&input[..len]).into(),
+                };
+                let (proof, public_inputs) = prover.prove(&mut rng, &circuit)?;
+                verifier.verify(&proof, &public_inputs)?;
+            }
+
+            // The elements past the length are ignored
+            let mut other = input;
+            other[4] = BlsScalar::from(42u64);
+            let circuit = VariableCircuit {
+                input: other,
+                len: 4,
+                hash: hash(&input[..4]).into(),
+            };
+            let (proof, public_inputs) = prover.prove(&mut rng, &circuit)?;
+            verifier.verify(&proof, &public_inputs)?;
+
+            // The length is bound to the hash
+            let circuit = VariableCircuit { len: 5, ..circuit };
+            assert!(prover.prove(&mut rng, &circuit).is_err());
+
+            // The length can't exceed the maximum
+            let circuit = VariableCircuit {
+                input,
+                len: MAX + 1,
+                hash: hash(&input).into(),
+            };
+            assert!(prover.prove(&mut rng, &circuit).is_err());
+
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,259 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Fiat-Shamir transcripts over the duplex sponge.
+//!
+//! Every operation absorbs its tag and its label before its values, in the
+//! [`DOMAIN_TRANSCRIPT`] domain of the sponge, and challenges are squeezed
+//! from the sponge, so every challenge depends on all the labels and values
+//! appended before it. Labels are prefixed with their length, so the
+//! absorbed elements can be parsed back into the operations that produced
+//! them, and two different sequences of operations never absorb the same
+//! elements. The gadget absorbs the tags and labels as constants of the
+//! circuit, and produces the same challenges as the native transcript.
+
+use dusk_bls12_381::BlsScalar;
+use dusk_jubjub::JubJubAffine;
+
+use crate::bytes::{limb, LIMB_BYTES};
+use crate::sponge::{Sponge, DOMAIN_TRANSCRIPT};
+
+#[cfg(feature = "plonk")]
+use crate::SpongeGadget;
+#[cfg(feature = "plonk")]
+use dusk_plonk::prelude::*;
+
+/// Tag of the label of the protocol.
+const OP_PROTOCOL: u64 = 0;
+/// Tag of an appended scalar.
+const OP_SCALAR: u64 = 1;
+/// Tag of an appended point.
+const OP_POINT: u64 = 2;
+/// Tag of a challenge.
+const OP_CHALLENGE: u64 = 3;
+
+/// Fiat-Shamir transcript appending field elements and points.
+#[derive(Debug, Clone, Copy)]
+pub struct HadesTranscript {
+    sponge: Sponge,
+}
+
+impl HadesTranscript {
+    /// Create a transcript for the protocol with the given label.
+    pub fn new(label: &[u8]) -> Self {
+        let mut sponge = Sponge::with_domain(DOMAIN_TRANSCRIPT);
+        absorb_label(&mut sponge, OP_PROTOCOL, label);
+
+        Self { sponge }
+    }
+
+    /// Append a scalar to the transcript.
+    pub fn append_scalar(&mut self, label: &[u8], scalar: &BlsScalar) {
+        absorb_label(&mut self.sponge, OP_SCALAR, label);
+        self.sponge.absorb(&[*scalar]);
+    }
+
+    /// Append a JubJub point to the transcript, through its affine
+    /// coordinates.
+    pub fn append_point(&mut self, label: &[u8], point: &JubJubAffine) {
+        absorb_label(&mut self.sponge, OP_POINT, label);
+        self.sponge.absorb(&[point.get_u(), point.get_v()]);
+    }
+
+    /// Compute a challenge from everything appended so far.
+    pub fn challenge_scalar(&mut self, label: &[u8]) -> BlsScalar {
+        absorb_label(&mut self.sponge, OP_CHALLENGE, label);
+        self.sponge.squeeze()
+    }
+}
+
+/// Fiat-Shamir transcript inside a plonk circuit, matching
+/// [`HadesTranscript`].
+#[cfg(feature = "plonk")]
+#[derive(Debug, Clone, Copy)]
+pub struct TranscriptGadget {
+    sponge: SpongeGadget,
+}
+
+#[cfg(feature = "plonk")]
+impl TranscriptGadget {
+    /// Create a transcript for the protocol with the given label.
+    pub fn new(composer: &mut Composer, label: &[u8]) -> Self {
+        let mut sponge = SpongeGadget::with_domain(composer, DOMAIN_TRANSCRIPT);
+        absorb_label_gadget(composer, &mut sponge, OP_PROTOCOL, label);
+
+        Self { sponge }
+    }
+
+    /// Append a scalar to the transcript.
+    pub fn append_scalar(&mut self, composer: &mut Composer, label: &[u8], scalar: Witness) {
+        absorb_label_gadget(composer, &mut self.sponge, OP_SCALAR, label);
+        self.sponge.absorb(composer, &[scalar]);
+    }
+
+    /// Append a JubJub point to the transcript, through its affine
+    /// coordinates.
+    pub fn append_point(&mut self, composer: &mut Composer, label: &[u8], point: WitnessPoint) {
+        absorb_label_gadget(composer, &mut self.sponge, OP_POINT, label);
+        self.sponge.absorb(composer, &[*point.x(), *point.y()]);
+    }
+
+    /// Compute a challenge from everything appended so far.
+    pub fn challenge_scalar(&mut self, composer: &mut Composer, label: &[u8]) -> Witness {
+        absorb_label_gadget(composer, &mut self.sponge, OP_CHALLENGE, label);
+        self.sponge.squeeze(composer)
+    }
+}
+
+/// Absorb the tag of an operation, followed by the length of its label in
+/// bytes and the limbs of the label.
+fn absorb_label(sponge: &mut Sponge, op: u64, label: &[u8]) {
+    sponge.absorb(&[BlsScalar::from(op), BlsScalar::from(label.len() as u64)]);
+    label
+        .chunks(LIMB_BYTES)
+        .for_each(|chunk| sponge.absorb(&[limb(chunk)]));
+}
+
+/// Absorb the tag of an operation and its label as constants of the
+/// circuit, matching [`absorb_label`].
+#[cfg(feature = "plonk")]
+fn absorb_label_gadget(composer: &mut Composer, sponge: &mut SpongeGadget, op: u64, label: &[u8]) {
+    let op = composer.append_constant(BlsScalar::from(op));
+    let len = composer.append_constant(BlsScalar::from(label.len() as u64));
+    sponge.absorb(composer, &[op, len]);
+
+    label.chunks(LIMB_BYTES).for_each(|chunk| {
+        let limb = composer.append_constant(limb(chunk));
+        sponge.absorb(composer, &[limb]);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dusk_jubjub::GENERATOR;
+
+    fn challenges(scalar: &BlsScalar, point: &JubJubAffine) -> [BlsScalar; 2] {
+        let mut transcript = HadesTranscript::new(b"test protocol");
+
+        transcript.append_scalar(b"scalar", scalar);
+        let first = transcript.challenge_scalar(b"first");
+
+        transcript.append_point(b"point", point);
+        let second = transcript.challenge_scalar(b"second");
+
+        [first, second]
+    }
+
+    #[test]
+    fn transcript() {
+        let scalar = BlsScalar::from(42u64);
+        let [first, second] = challenges(&scalar, &GENERATOR);
+
+        assert_eq!(challenges(&scalar, &GENERATOR), [first, second]);
+        assert_ne!(first, second);
+
+        // A challenge depends on everything appended before it only
+        let [a, b] = challenges(&scalar, &JubJubAffine::identity());
+        assert_eq!(a, first);
+        assert_ne!(b, second);
+
+        let [a, b] = challenges(&BlsScalar::one(), &GENERATOR);
+        assert_ne!(a, first);
+        assert_ne!(b, second);
+
+        // Labels are bound to the challenges
+        let mut transcript = HadesTranscript::new(b"test protocol");
+        transcript.append_scalar(b"other", &scalar);
+        assert_ne!(transcript.challenge_scalar(b"first"), first);
+
+        // Scalars and points absorbing the same elements don't collide
+        let zero = BlsScalar::zero();
+        let seven = BlsScalar::from(7u64);
+
+        let mut scalars = HadesTranscript::new(b"test protocol");
+        scalars.append_scalar(b"", &scalar);
+        scalars.append_scalar(b"", &zero);
+        scalars.append_scalar(b"", &seven);
+
+        let mut points = HadesTranscript::new(b"test protocol");
+        points.append_point(b"", &JubJubAffine::from_raw_unchecked(scalar, zero));
+        points.append_point(b"", &JubJubAffine::from_raw_unchecked(zero, seven));
+
+        assert_ne!(scalars.challenge_scalar(b""), points.challenge_scalar(b""));
+    }
+
+    #[cfg(feature = "plonk")]
+    mod gadget {
+        use super::*;
+        use dusk_plonk::prelude::Error;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        struct TranscriptCircuit {
+            scalar: BlsScalar,
+            point: JubJubAffine,
+            challenges: [BlsScalar; 2],
+        }
+
+        impl Default for TranscriptCircuit {
+            fn default() -> Self {
+                Self {
+                    scalar: BlsScalar::zero(),
+                    point: JubJubAffine::identity(),
+                    challenges: [BlsScalar::zero(); 2],
+                }
+            }
+        }
+
+        impl Circuit for TranscriptCircuit {
+            fn circuit(&self, composer: &mut Composer) -> Result<(), Error> {
+                let scalar = composer.append_witness(self.scalar);
+                let point = composer.append_point(self.point);
+
+                let mut transcript = TranscriptGadget::new(composer, b"test protocol");
+
+                transcript.append_scalar(composer, b"scalar", scalar);
+                let first = transcript.challenge_scalar(composer, b"first");
+
+                transcript.append_point(composer, b"point", point);
+                let second = transcript.challenge_scalar(composer, b"second");
+
+                for (witness, expected) in [first, second].iter().zip(self.challenges.iter()) {
+                    let public = composer.append_public(*expected);
+                    composer.assert_equal(*witness, public);
+                }
+
+                Ok(())
+            }
+        }
+
+        #[test]
+        fn transcript() -> Result<(), Error> {
+            let mut rng = StdRng::seed_from_u64(0xbeef);
+
+            let pp = PublicParameters::setup(1 << 13, &mut rng)?;
+            let (prover, verifier) = Compiler::compile::<TranscriptCircuit>(&pp, b"transcript")?;
+
+            let scalar = BlsScalar::from(42u64);
+            let circuit = TranscriptCircuit {
+                scalar,
+                point: GENERATOR,
+                challenges: challenges(&scalar, &GENERATOR),
+            };
+            let (proof, public_inputs) = prover.prove(&mut rng, &circuit)?;
+            verifier.verify(&proof, &public_inputs)?;
+
+            let circuit = TranscriptCircuit {
+                scalar: BlsScalar::one(),
+                ..circuit
+            };
+            assert!(prover.prove(&mut rng, &circuit).is_err());
+
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,465 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Winternitz one-time signatures over the `Hades252` permutation.
+//!
+//! The message is split into base-[`WINTERNITZ`] digits of two bits, least
+//! significant first, followed by the digits of the checksum
+//! `Σ (WINTERNITZ - 1 - digit)`, so increasing a digit of the message
+//! decreases one of the checksum. Every digit selects a position in its own
+//! hash chain, from the secret value at the start to the public value at
+//! the end, and the signature holds the value of every chain at the
+//! position of its digit.
+//!
+//! A step of a chain permutes `[capacity, value, seed, tweak, 0]` and takes
+//! the first element of the rate, where the capacity is the one of the
+//! [`DOMAIN_WOTS`] domain, the seed is public, and the tweak is unique to
+//! the key, the chain and the step. The ends of the chains are compressed
+//! into the public key in the [`DOMAIN_WOTS_KEY`] domain of the sponge.
+//!
+//! Signing two different messages with the same key reveals enough values
+//! to forge signatures, so [`WotsSecretKey::sign`] consumes the key.
+
+use core::fmt;
+
+use dusk_bls12_381::BlsScalar;
+
+use crate::sponge::{capacity, Sponge, DOMAIN_WOTS, DOMAIN_WOTS_KEY};
+use crate::{HadesDigest, HadesKdf, ScalarStrategy, Strategy, WIDTH};
+
+#[cfg(feature = "plonk")]
+use crate::jubjub::canonical_bits_gadget;
+#[cfg(feature = "plonk")]
+use crate::{GadgetStrategy, SpongeGadget};
+#[cfg(feature = "plonk")]
+use dusk_plonk::prelude::*;
+
+/// Base of the digits signed by a chain.
+pub const WINTERNITZ: usize = 4;
+
+/// Amount of digits of the message.
+const MESSAGE_DIGITS: usize = 128;
+
+/// Amount of digits of the checksum, which is at most
+/// `MESSAGE_DIGITS · (WINTERNITZ - 1) < 2^10`.
+const CHECKSUM_DIGITS: usize = 5;
+
+/// Amount of hash chains of a key, one per signed digit.
+pub const WOTS_CHAINS: usize = MESSAGE_DIGITS + CHECKSUM_DIGITS;
+
+/// Secret key signing a single message.
+pub struct WotsSecretKey {
+    chains: [BlsScalar; WOTS_CHAINS],
+    seed: BlsScalar,
+    index: u64,
+}
+
+impl fmt::Debug for WotsSecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WotsSecretKey")
+            .field("seed", &self.seed)
+            .field("index", &self.index)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WotsSecretKey {
+    /// Derive the key with the given `index` from a secret, for the public
+    /// `seed`.
+    ///
+    /// Keys with different indices are independent, and may be derived
+    /// from the same secret.
+    pub fn new(secret: &BlsScalar, seed: BlsScalar, index: u64) -> Self {
+        let mut label = [0u8; 12];
+        label[..4].copy_from_slice(b"wots");
+        label[4..].copy_from_slice(&index.to_le_bytes());
+
+        let mut chains = [BlsScalar::zero(); WOTS_CHAINS];
+        HadesKdf::from_prk(*secret).expand(&label, &mut chains);
+
+        Self {
+            chains,
+            seed,
+            index,
+        }
+    }
+
+    /// Public key of the secret key.
+    pub fn public_key(&self) -> WotsPublicKey {
+        let mut ends = self.chains;
+        ends.iter_mut().enumerate().for_each(|(chain, value)| {
+            *value = walk(value, &self.seed, self.index, chain, 0, WINTERNITZ - 1);
+        });
+
+        WotsPublicKey {
+            key: compress(&ends),
+            seed: self.seed,
+            index: self.index,
+        }
+    }
+
+    /// Sign a message, consuming the key.
+    pub fn sign(self, message: &BlsScalar) -> WotsSignature {
+        let digits = digits(message);
+
+        let mut chains = self.chains;
+        chains
+            .iter_mut()
+            .zip(digits.iter())
+            .enumerate()
+            .for_each(|(chain, (value, digit))| {
+                *value = walk(value, &self.seed, self.index, chain, 0, *digit);
+            });
+
+        WotsSignature(chains)
+    }
+}
+
+/// Public key verifying the signature of a single message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WotsPublicKey {
+    key: HadesDigest,
+    seed: BlsScalar,
+    index: u64,
+}
+
+impl WotsPublicKey {
+    /// Compressed ends of the chains.
+    pub const fn key(&self) -> &HadesDigest {
+        &self.key
+    }
+
+    /// Public seed of the chains.
+    pub const fn seed(&self) -> &BlsScalar {
+        &self.seed
+    }
+
+    /// Index of the key.
+    pub const fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// Check that `signature` signs `message` under this key.
+    pub fn verify(&self, message: &BlsScalar, signature: &WotsSignature) -> bool {
+        signature.public_key(message, &self.seed, self.index) == self.key
+    }
+}
+
+/// Signature of a message, holding the value of every chain at the position
+/// of its digit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WotsSignature([BlsScalar; WOTS_CHAINS]);
+
+impl Default for WotsSignature {
+    fn default() -> Self {
+        Self([BlsScalar::zero(); WOTS_CHAINS])
+    }
+}
+
+impl WotsSignature {
+    /// Create a signature from the values of its chains.
+    pub const fn new(chains: [BlsScalar; WOTS_CHAINS]) -> Self {
+        Self(chains)
+    }
+
+    /// Values of the chains.
+    pub const fn chains(&self) -> &[BlsScalar; WOTS_CHAINS] {
+        &self.0
+    }
+
+    /// Compute the compressed public key of the key with the given `seed`
+    /// and `index` under which this signature signs `message`.
+    pub fn public_key(&self, message: &BlsScalar, seed: &BlsScalar, index: u64) -> HadesDigest {
+        let digits = digits(message);
+
+        let mut ends = self.0;
+        ends.iter_mut()
+            .zip(digits.iter())
+            .enumerate()
+            .for_each(|(chain, (value, digit))| {
+                *value = walk(value, seed, index, chain, *digit, WINTERNITZ - 1 - digit);
+            });
+
+        compress(&ends)
+    }
+}
+
+/// Digits of the message followed by the digits of the checksum.
+fn digits(message: &BlsScalar) -> [usize; WOTS_CHAINS] {
+    let bytes = message.to_bytes();
+    let mut digits = [0usize; WOTS_CHAINS];
+
+    digits[..MESSAGE_DIGITS]
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, digit)| *digit = ((bytes[i / 4] >> (2 * (i % 4))) & 3) as usize);
+
+    let checksum: usize = digits[..MESSAGE_DIGITS]
+        .iter()
+        .map(|digit| WINTERNITZ - 1 - digit)
+        .sum();
+
+    digits[MESSAGE_DIGITS..]
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, digit)| *digit = (checksum >> (2 * i)) & 3);
+
+    digits
+}
+
+/// Tweak of the given step of a chain of the key with the given index.
+fn tweak(index: u64, chain: usize, step: usize) -> BlsScalar {
+    BlsScalar::from(index) * BlsScalar::from(1 << 16) + BlsScalar::from((chain << 8 | step) as u64)
+}
+
+/// Walk `steps` steps of a chain from the position `start`.
+fn walk(
+    value: &BlsScalar,
+    seed: &BlsScalar,
+    index: u64,
+    chain: usize,
+    start: usize,
+    steps: usize,
+) -> BlsScalar {
+    (start..start + steps).fold(*value, |value, step| {
+        let mut state = [BlsScalar::zero(); WIDTH];
+
+        state[0] = capacity(DOMAIN_WOTS);
+        state[1] = value;
+        state[2] = *seed;
+        state[3] = tweak(index, chain, step);
+
+        ScalarStrategy::new().perm(&mut state);
+
+        state[1]
+    })
+}
+
+/// Compress the ends of the chains into a public key.
+fn compress(ends: &[BlsScalar; WOTS_CHAINS]) -> HadesDigest {
+    let mut sponge = Sponge::with_domain(DOMAIN_WOTS_KEY);
+    sponge.absorb(ends);
+    sponge.finalize().into()
+}
+
+/// Compute, inside a plonk circuit, the compressed public key under which
+/// `signature` signs `message`, matching [`WotsSignature::public_key`].
+///
+/// The values of the signature are appended as private witnesses, and the
+/// digits are taken from the canonical bits of `message`. Every chain costs
+/// `WINTERNITZ - 1` permutations, whether its steps are used or not. The
+/// returned key is left unconstrained, so the caller can compare it with
+/// the key it expects.
+///
+/// A signature costs about 425 000 gates, so a circuit verifying one needs
+/// public parameters of at least `1 << 19`.
+#[cfg(feature = "plonk")]
+pub fn wots_gadget(
+    composer: &mut Composer,
+    seed: Witness,
+    index: Witness,
+    message: Witness,
+    signature: &WotsSignature,
+) -> Witness {
+    let bits = canonical_bits_gadget(composer, message);
+
+    // The last digit of the message only has its low bit
+    let mut digits = [(Composer::ZERO, Composer::ZERO); WOTS_CHAINS];
+    digits[..MESSAGE_DIGITS]
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, digit)| {
+            let high = bits.get(2 * i + 1).copied().unwrap_or(Composer::ZERO);
+            *digit = (bits[2 * i], high);
+        });
+
+    // checksum = Σ (3 - digit)
+    let max = (MESSAGE_DIGITS * (WINTERNITZ - 1)) as u64;
+    let checksum = digits[..MESSAGE_DIGITS].iter().fold(
+        composer.append_constant(BlsScalar::from(max)),
+        |sum, (low, high)| {
+            let constraint = Constraint::new()
+                .left(1)
+                .a(sum)
+                .right(-BlsScalar::one())
+                .b(*low)
+                .fourth(-BlsScalar::from(2))
+                .d(*high);
+            composer.gate_add(constraint)
+        },
+    );
+
+    let checksum = composer.component_decomposition::<{ 2 * CHECKSUM_DIGITS }>(checksum);
+    digits[MESSAGE_DIGITS..]
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, digit)| *digit = (checksum[2 * i], checksum[2 * i + 1]));
+
+    let capacity = composer.append_constant(capacity(DOMAIN_WOTS));
+
+    let mut ends = [Composer::ZERO; WOTS_CHAINS];
+    ends.iter_mut()
+        .zip(digits.iter())
+        .zip(signature.0.iter())
+        .enumerate()
+        .for_each(|(chain, ((end, (low, high)), value))| {
+            // The chain takes the step `j` if its digit is at most `j`
+            let constraint = Constraint::new().mult(1).a(*low).b(*high);
+            let both = composer.gate_mul(constraint);
+
+            let constraint = Constraint::new()
+                .left(1)
+                .a(both)
+                .right(-BlsScalar::one())
+                .b(*low)
+                .fourth(-BlsScalar::one())
+                .d(*high)
+                .constant(BlsScalar::one());
+            let zero = composer.gate_add(constraint);
+
+            let constraint = Constraint::new()
+                .left(-BlsScalar::one())
+                .a(*high)
+                .constant(BlsScalar::one());
+            let at_most_one = composer.gate_add(constraint);
+
+            let constraint = Constraint::new()
+                .left(-BlsScalar::one())
+                .a(both)
+                .constant(BlsScalar::one());
+            let at_most_two = composer.gate_add(constraint);
+
+            let mut node = composer.append_witness(*value);
+            for (step, take) in [zero, at_most_one, at_most_two].iter().enumerate() {
+                let constraint = Constraint::new()
+                    .left(BlsScalar::from(1 << 16))
+                    .a(index)
+                    .constant(BlsScalar::from((chain << 8 | step) as u64));
+                let tweak = composer.gate_add(constraint);
+
+                let mut state = [capacity, node, seed, tweak, Composer::ZERO];
+                GadgetStrategy::gadget(composer, &mut state);
+
+                node = composer.component_select(*take, state[1], node);
+            }
+
+            *end = node;
+        });
+
+    let mut sponge = SpongeGadget::with_domain(composer, DOMAIN_WOTS_KEY);
+    sponge.absorb(composer, &ends);
+    sponge.finalize(composer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: BlsScalar = BlsScalar::from_raw([42, 0, 0, 0]);
+    const SEED: BlsScalar = BlsScalar::from_raw([7, 0, 0, 0]);
+
+    #[test]
+    fn digits() {
+        let digits = super::digits(&-BlsScalar::one());
+
+        // The checksum balances the digits of the message
+        let sum: usize = digits[..MESSAGE_DIGITS].iter().sum();
+        let checksum = digits[MESSAGE_DIGITS..]
+            .iter()
+            .rev()
+            .fold(0, |checksum, digit| checksum * WINTERNITZ + digit);
+        assert_eq!(sum + checksum, MESSAGE_DIGITS * (WINTERNITZ - 1));
+
+        assert!(super::digits(&BlsScalar::zero())[..MESSAGE_DIGITS]
+            .iter()
+            .all(|digit| *digit == 0));
+    }
+
+    #[test]
+    fn signature() {
+        let message = BlsScalar::from(0xdead_beefu64);
+
+        let key = WotsSecretKey::new(&SECRET, SEED, 3);
+        let public_key = key.public_key();
+        let signature = key.sign(&message);
+
+        assert!(public_key.verify(&message, &signature));
+        assert!(!public_key.verify(&(message + BlsScalar::one()), &signature));
+
+        let mut forged = *signature.chains();
+        forged[0] += BlsScalar::one();
+        assert!(!public_key.verify(&message, &WotsSignature::new(forged)));
+
+        // Keys of different indices are independent
+        let other = WotsSecretKey::new(&SECRET, SEED, 4).public_key();
+        assert_ne!(other.key(), public_key.key());
+        assert!(!other.verify(&message, &signature));
+    }
+
+    #[cfg(feature = "plonk")]
+    mod gadget {
+        use super::*;
+        use dusk_plonk::prelude::Error;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        const INDEX: u64 = 3;
+
+        #[derive(Default)]
+        struct WotsCircuit {
+            message: BlsScalar,
+            signature: WotsSignature,
+            key: BlsScalar,
+        }
+
+        impl Circuit for WotsCircuit {
+            fn circuit(&self, composer: &mut Composer) -> Result<(), Error> {
+                let seed = composer.append_constant(SEED);
+                let index = composer.append_constant(BlsScalar::from(INDEX));
+                let message = composer.append_public(self.message);
+
+                let key = wots_gadget(composer, seed, index, message, &self.signature);
+
+                let public = composer.append_public(self.key);
+                composer.assert_equal(key, public);
+
+                Ok(())
+            }
+        }
+
+        #[test]
+        fn verify() -> Result<(), Error> {
+            let mut rng = StdRng::seed_from_u64(0xbeef);
+
+            let pp = PublicParameters::setup(1 << 19, &mut rng)?;
+            let (prover, verifier) = Compiler::compile::<WotsCircuit>(&pp, b"wots")?;
+
+            let message = BlsScalar::from(0xdead_beefu64);
+            let key = WotsSecretKey::new(&SECRET, SEED, INDEX);
+            let public_key = key.public_key();
+            let signature = key.sign(&message);
+
+            let circuit = WotsCircuit {
+                message,
+                signature,
+                key: (*public_key.key()).into(),
+            };
+            let (proof, public_inputs) = prover.prove(&mut rng, &circuit)?;
+            verifier.verify(&proof, &public_inputs)?;
+
+            // A forged chain value doesn't lead to the public key
+            let mut forged = *signature.chains();
+            forged[0] += BlsScalar::one();
+            let circuit = WotsCircuit {
+                signature: WotsSignature::new(forged),
+                ..circuit
+            };
+            assert!(prover.prove(&mut rng, &circuit).is_err());
+
+            Ok(())
+        }
+    }
+}
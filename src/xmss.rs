@@ -0,0 +1,346 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Many-time signatures in the style of XMSS.
+//!
+//! The leaves of a [`MerkleTree`] of height `H` are the compressed public
+//! keys of `2^H` Winternitz one-time keys, and the public key is the root
+//! of the tree along with the seed of the chains. A signature is the
+//! one-time signature of the next unused key, with the opening of its
+//! leaf.
+//!
+//! The index of the next unused key is the only state of the secret key.
+//! It must be persisted after every signature, before the signature is
+//! published, and restored with [`XmssSecretKey::with_next`]: signing again
+//! from a stale index reuses one-time keys, which reveals enough of their
+//! chains to forge signatures.
+
+use core::fmt;
+
+use alloc::vec::Vec;
+use dusk_bls12_381::BlsScalar;
+
+use crate::wots::{WotsSecretKey, WotsSignature};
+use crate::{Error, HadesDigest, MerkleTree, Opening};
+
+#[cfg(feature = "plonk")]
+use crate::merkle::{append_opening, root_gadget};
+#[cfg(feature = "plonk")]
+use crate::wots_gadget;
+#[cfg(feature = "plonk")]
+use dusk_plonk::prelude::*;
+
+/// Secret key signing up to `2^H` messages.
+///
+/// The key holds the index of the next unused one-time key. It doesn't
+/// implement `Clone`, since two copies would sign with the same one-time
+/// keys, but that doesn't prevent restoring it twice from the same secret
+/// and index: the caller is responsible for persisting [`Self::next`].
+pub struct XmssSecretKey<const H: usize> {
+    secret: BlsScalar,
+    seed: BlsScalar,
+    tree: MerkleTree<H>,
+    next: u64,
+}
+
+impl<const H: usize> fmt::Debug for XmssSecretKey<H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("XmssSecretKey")
+            .field("seed", &self.seed)
+            .field("next", &self.next)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<const H: usize> XmssSecretKey<H> {
+    /// Derive every one-time key from a secret, for the public `seed`, and
+    /// build the tree of their public keys.
+    ///
+    /// All the `2^H` one-time public keys are computed, so `H` should stay
+    /// small.
+    pub fn new(secret: BlsScalar, seed: BlsScalar) -> Self {
+        Self::build(secret, seed, 0)
+    }
+
+    /// Restore a key whose first `next` one-time keys were already used,
+    /// from the index persisted with [`Self::next`].
+    ///
+    /// Returns [`Error::KeysExhausted`] if `next` is larger than `2^H`.
+    pub fn with_next(secret: BlsScalar, seed: BlsScalar, next: u64) -> Result<Self, Error> {
+        if next > 1 << H {
+            return Err(Error::KeysExhausted);
+        }
+
+        Ok(Self::build(secret, seed, next))
+    }
+
+    /// Build the tree of one-time public keys.
+    fn build(secret: BlsScalar, seed: BlsScalar, next: u64) -> Self {
+        let leaves: Vec<_> = (0..1u64 << H)
+            .map(|index| {
+                let key = WotsSecretKey::new(&secret, seed, index).public_key();
+                BlsScalar::from(*key.key())
+            })
+            .collect();
+
+        let tree = MerkleTree::from_leaves(&leaves).expect("the leaves fill the tree");
+
+        Self {
+            secret,
+            seed,
+            tree,
+            next,
+        }
+    }
+
+    /// Public key of the secret key.
+    pub fn public_key(&self) -> XmssPublicKey {
+        XmssPublicKey {
+            root: self.tree.root().expect("Reading from memory can't fail"),
+            seed: self.seed,
+        }
+    }
+
+    /// Index of the next unused one-time key.
+    ///
+    /// It must be persisted after every call to [`Self::sign`], before the
+    /// signature is published.
+    pub const fn next(&self) -> u64 {
+        self.next
+    }
+
+    /// Amount of messages the key can still sign.
+    pub const fn remaining(&self) -> u64 {
+        (1 << H) - self.next
+    }
+
+    /// Sign a message with the next unused one-time key.
+    ///
+    /// The index of the key is advanced before signing, and the new
+    /// [`Self::next`] must be persisted before the signature is published.
+    ///
+    /// Returns [`Error::KeysExhausted`] once every one-time key was used.
+    pub fn sign(&mut self, message: &BlsScalar) -> Result<XmssSignature<H>, Error> {
+        if self.remaining() == 0 {
+            return Err(Error::KeysExhausted);
+        }
+
+        let index = self.next;
+        self.next += 1;
+
+        let wots = WotsSecretKey::new(&self.secret, self.seed, index).sign(message);
+        let opening = self.tree.opening(index)?.expect("the leaf is in the tree");
+
+        Ok(XmssSignature { wots, opening })
+    }
+}
+
+/// Public key verifying the signatures of an [`XmssSecretKey`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct XmssPublicKey {
+    root: HadesDigest,
+    seed: BlsScalar,
+}
+
+impl XmssPublicKey {
+    /// Root of the tree of one-time public keys.
+    pub const fn root(&self) -> &HadesDigest {
+        &self.root
+    }
+
+    /// Public seed of the chains.
+    pub const fn seed(&self) -> &BlsScalar {
+        &self.seed
+    }
+
+    /// Check that `signature` signs `message` under this key.
+    pub fn verify<const H: usize>(
+        &self,
+        message: &BlsScalar,
+        signature: &XmssSignature<H>,
+    ) -> bool {
+        let index = signature.opening.position();
+        let leaf = signature.wots.public_key(message, &self.seed, index);
+
+        signature.opening.verify(leaf.as_scalar(), &self.root)
+    }
+}
+
+/// Signature of a message under an [`XmssPublicKey`] whose tree has height
+/// `H`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct XmssSignature<const H: usize> {
+    wots: WotsSignature,
+    opening: Opening<H>,
+}
+
+impl<const H: usize> XmssSignature<H> {
+    /// One-time signature of the message.
+    pub const fn wots(&self) -> &WotsSignature {
+        &self.wots
+    }
+
+    /// Opening of the one-time public key, whose position is the index of
+    /// the one-time key.
+    pub const fn opening(&self) -> &Opening<H> {
+        &self.opening
+    }
+}
+
+/// Compute, inside a plonk circuit, the root of the tree of one-time public
+/// keys under which `signature` signs `message`, matching
+/// [`XmssPublicKey::verify`].
+///
+/// The index of the one-time key is taken from the position bits of the
+/// opening, and the signature is appended as private witnesses. The
+/// returned root is left unconstrained, so the caller can compare it with
+/// a public input.
+///
+/// The cost is the one of [`wots_gadget`], plus a permutation for every
+/// level of the tree.
+#[cfg(feature = "plonk")]
+pub fn xmss_gadget<const H: usize>(
+    composer: &mut Composer,
+    seed: Witness,
+    message: Witness,
+    signature: &XmssSignature<H>,
+) -> Witness {
+    let (branch, bits) = append_opening(composer, &signature.opening);
+
+    let index = bits
+        .iter()
+        .enumerate()
+        .fold(Composer::ZERO, |index, (level, bit)| {
+            let constraint = Constraint::new()
+                .left(1)
+                .a(index)
+                .right(BlsScalar::from(1u64 << level))
+                .b(*bit);
+            composer.gate_add(constraint)
+        });
+
+    let leaf = wots_gadget(composer, seed, index, message, &signature.wots);
+
+    root_gadget(composer, leaf, &branch, &bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const H: usize = 2;
+
+    const SECRET: BlsScalar = BlsScalar::from_raw([42, 0, 0, 0]);
+    const SEED: BlsScalar = BlsScalar::from_raw([7, 0, 0, 0]);
+
+    #[test]
+    fn signatures() {
+        let mut key = XmssSecretKey::<H>::new(SECRET, SEED);
+        let public_key = key.public_key();
+
+        for i in 0..4u64 {
+            let message = BlsScalar::from(i);
+            let signature = key.sign(&message).unwrap();
+
+            assert_eq!(signature.opening().position(), i);
+            assert!(public_key.verify(&message, &signature));
+            assert!(!public_key.verify(&BlsScalar::from(42u64), &signature));
+        }
+
+        assert_eq!(key.remaining(), 0);
+        assert_eq!(key.sign(&BlsScalar::one()), Err(Error::KeysExhausted));
+
+        // A signature only verifies under the tree it was made for
+        let other = XmssSecretKey::<H>::new(SECRET, BlsScalar::one()).public_key();
+        let signature = XmssSecretKey::<H>::new(SECRET, SEED)
+            .sign(&BlsScalar::one())
+            .unwrap();
+        assert!(!other.verify(&BlsScalar::one(), &signature));
+    }
+
+    #[test]
+    fn restore() {
+        let mut key = XmssSecretKey::<H>::new(SECRET, SEED);
+        key.sign(&BlsScalar::one()).unwrap();
+        assert_eq!(key.next(), 1);
+
+        // A restored key resumes from the persisted index
+        let mut restored = XmssSecretKey::<H>::with_next(SECRET, SEED, key.next()).unwrap();
+        assert_eq!(restored.public_key(), key.public_key());
+        assert_eq!(restored.remaining(), 3);
+        assert_eq!(
+            restored.sign(&BlsScalar::one()),
+            key.sign(&BlsScalar::one())
+        );
+        assert_eq!(key.sign(&BlsScalar::one()).unwrap().opening().position(), 2);
+
+        let mut exhausted = XmssSecretKey::<H>::with_next(SECRET, SEED, 4).unwrap();
+        assert_eq!(exhausted.sign(&BlsScalar::one()), Err(Error::KeysExhausted));
+        assert!(XmssSecretKey::<H>::with_next(SECRET, SEED, 5).is_err());
+    }
+
+    #[cfg(feature = "plonk")]
+    mod gadget {
+        use super::*;
+        use dusk_plonk::prelude::Error;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        #[derive(Default)]
+        struct XmssCircuit {
+            seed: BlsScalar,
+            message: BlsScalar,
+            signature: XmssSignature<H>,
+            root: BlsScalar,
+        }
+
+        impl Circuit for XmssCircuit {
+            fn circuit(&self, composer: &mut Composer) -> Result<(), Error> {
+                let seed = composer.append_public(self.seed);
+                let message = composer.append_public(self.message);
+
+                let root = xmss_gadget(composer, seed, message, &self.signature);
+
+                let public = composer.append_public(self.root);
+                composer.assert_equal(root, public);
+
+                Ok(())
+            }
+        }
+
+        #[test]
+        fn verify() -> Result<(), Error> {
+            let mut rng = StdRng::seed_from_u64(0xbeef);
+
+            let pp = PublicParameters::setup(1 << 19, &mut rng)?;
+            let (prover, verifier) = Compiler::compile::<XmssCircuit>(&pp, b"xmss")?;
+
+            let mut key = XmssSecretKey::<H>::new(SECRET, SEED);
+            let public_key = key.public_key();
+
+            key.sign(&BlsScalar::zero()).unwrap();
+            let message = -BlsScalar::one();
+            let signature = key.sign(&message).unwrap();
+
+            let circuit = XmssCircuit {
+                seed: SEED,
+                message,
+                signature,
+                root: (*public_key.root()).into(),
+            };
+            let (proof, public_inputs) = prover.prove(&mut rng, &circuit)?;
+            verifier.verify(&proof, &public_inputs)?;
+
+            let circuit = XmssCircuit {
+                message: BlsScalar::one(),
+                ..circuit
+            };
+            assert!(prover.prove(&mut rng, &circuit).is_err());
+
+            Ok(())
+        }
+    }
+}